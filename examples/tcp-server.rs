@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::net::{TcpListener, TcpStream};
+
+use polling::{Event, Poller};
+
+fn main() -> io::Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:8000")?;
+    listener.set_nonblocking(true)?;
+
+    let poller = Poller::new()?;
+    poller.add(&listener, Event::readable(0))?;
+
+    let mut connections: HashMap<usize, TcpStream> = HashMap::new();
+    let mut next_key = 1;
+
+    let mut events = Vec::new();
+    loop {
+        events.clear();
+        poller.wait(&mut events, None)?;
+
+        for ev in &events {
+            if ev.key == 0 {
+                // Accept as many connections as are pending.
+                loop {
+                    match listener.accept() {
+                        Ok((stream, addr)) => {
+                            println!("Accepted {}", addr);
+                            stream.set_nonblocking(true)?;
+                            poller.add(&stream, Event::readable(next_key))?;
+                            connections.insert(next_key, stream);
+                            next_key += 1;
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(e) => return Err(e),
+                    }
+                }
+                poller.modify(&listener, Event::readable(0))?;
+            } else if let Some(stream) = connections.get_mut(&ev.key) {
+                let mut buf = [0; 1024];
+                match stream.read(&mut buf) {
+                    Ok(0) => {
+                        println!("Closed connection {}", ev.key);
+                        poller.delete(&*stream)?;
+                        connections.remove(&ev.key);
+                    }
+                    Ok(n) => {
+                        println!("Read {} bytes from connection {}", n, ev.key);
+                        poller.modify(&*stream, Event::readable(ev.key))?;
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        poller.modify(&*stream, Event::readable(ev.key))?;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Supervision of child processes through a pidfd.
+
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::process::{Child, ExitStatus};
+
+use crate::{sys, Event, Poller};
+
+/// What an event delivered for a [`ChildMonitor`] means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildEvent {
+    /// The child process exited, and can be reaped with [`ChildMonitor::try_reap()`].
+    Exited,
+    /// The child's stdout is readable, or was closed.
+    Stdout,
+    /// The child's stderr is readable, or was closed.
+    Stderr,
+}
+
+/// A child process whose exit and captured output are reported by a [`Poller`].
+///
+/// The monitor adds a pidfd for the child to the poller under `key`, and the child's captured
+/// stdout and stderr pipes, if any, under `key + 1` and `key + 2`. All of them are added with
+/// readable interest, which is disabled once an event is delivered like for any other source,
+/// and can be re-enabled with [`rearm()`][`ChildMonitor::rearm()`]. The sources are deleted from
+/// the poller when the monitor is dropped.
+///
+/// The monitor borrows the child, so the caller keeps it, even if creating the monitor fails.
+///
+/// A pidfd refers to the process itself rather than to its process ID, so it becomes readable
+/// exactly when this child exits, even if its process ID has been reused since. Pidfds require
+/// Linux 5.3 or newer.
+///
+/// This type is only available on Linux.
+///
+/// # Examples
+///
+/// ```no_run
+/// use polling::{ChildEvent, ChildMonitor, Poller};
+/// use std::process::{Command, Stdio};
+///
+/// let poller = Poller::new()?;
+/// let mut child = Command::new("echo").arg("hello").stdout(Stdio::piped()).spawn()?;
+/// let mut monitor = ChildMonitor::new(&poller, &mut child, 10)?;
+///
+/// let mut events = Vec::new();
+/// loop {
+///     events.clear();
+///     poller.wait(&mut events, None)?;
+///
+///     for ev in &events {
+///         match monitor.event(ev) {
+///             Some(ChildEvent::Exited) => {
+///                 let status = monitor.try_reap()?;
+///                 println!("exited: {:?}", status);
+///                 return Ok(());
+///             }
+///             Some(ChildEvent::Stdout) => {
+///                 // Read from `monitor.child().stdout`, then:
+///                 monitor.rearm(ChildEvent::Stdout)?;
+///             }
+///             _ => {}
+///         }
+///     }
+/// }
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug)]
+pub struct ChildMonitor<'a> {
+    poller: &'a Poller,
+    child: &'a mut Child,
+    pidfd: RawFd,
+    key: usize,
+}
+
+impl<'a> ChildMonitor<'a> {
+    /// Starts monitoring a child process, using keys from `key` to `key + 2`.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the kernel doesn't support pidfds, or if `key + 2` overflows or
+    /// equals `usize::MAX`.
+    #[allow(clippy::incompatible_msrv)]
+    pub fn new(
+        poller: &'a Poller,
+        child: &'a mut Child,
+        key: usize,
+    ) -> io::Result<ChildMonitor<'a>> {
+        if key >= std::usize::MAX - 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the keys of a child monitor must not include `usize::MAX`",
+            ));
+        }
+
+        // The pidfd is always opened with `FD_CLOEXEC`.
+        let pidfd = syscall!(syscall(
+            libc::SYS_pidfd_open,
+            child.id() as libc::pid_t,
+            0 as libc::c_uint,
+        ))
+        .and_then(sys::fd_from_syscall)?;
+
+        let monitor = ChildMonitor {
+            poller,
+            child,
+            pidfd,
+            key,
+        };
+        // On failure, dropping the monitor deletes whatever was added.
+        for &which in &[ChildEvent::Exited, ChildEvent::Stdout, ChildEvent::Stderr] {
+            if let Some(fd) = monitor.fd(which) {
+                poller.add(fd, Event::readable(monitor.key(which)))?;
+            }
+        }
+        Ok(monitor)
+    }
+
+    /// Returns the child process, to read its output or send it input.
+    ///
+    /// Don't take stdout or stderr out of the child while it is monitored, as they would be
+    /// closed while still in the poller.
+    pub fn child(&mut self) -> &mut Child {
+        self.child
+    }
+
+    /// Returns the key events of a kind are delivered with.
+    pub fn key(&self, which: ChildEvent) -> usize {
+        match which {
+            ChildEvent::Exited => self.key,
+            ChildEvent::Stdout => self.key + 1,
+            ChildEvent::Stderr => self.key + 2,
+        }
+    }
+
+    /// Tells what an event means for this child, or `None` if it is for another source.
+    pub fn event(&self, ev: &Event) -> Option<ChildEvent> {
+        [ChildEvent::Exited, ChildEvent::Stdout, ChildEvent::Stderr]
+            .iter()
+            .cloned()
+            .find(|&which| ev.key == self.key(which) && self.fd(which).is_some())
+    }
+
+    /// Re-enables interest in events of a kind.
+    ///
+    /// This does nothing for stdout or stderr if it isn't captured.
+    pub fn rearm(&self, which: ChildEvent) -> io::Result<()> {
+        match self.fd(which) {
+            Some(fd) => self.poller.modify(fd, Event::readable(self.key(which))),
+            None => Ok(()),
+        }
+    }
+
+    /// Reaps the child if it has exited, returning its exit status.
+    ///
+    /// This is [`Child::try_wait()`], which is safe from process ID reuse because the process
+    /// ID of a child can't be reused until the child is reaped. It returns `Ok(None)` if the
+    /// child is still running.
+    pub fn try_reap(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.child().try_wait()
+    }
+
+    /// Returns the file descriptor behind events of a kind.
+    fn fd(&self, which: ChildEvent) -> Option<RawFd> {
+        match which {
+            ChildEvent::Exited => Some(self.pidfd),
+            ChildEvent::Stdout => self.child.stdout.as_ref().map(|s| s.as_raw_fd()),
+            ChildEvent::Stderr => self.child.stderr.as_ref().map(|s| s.as_raw_fd()),
+        }
+    }
+}
+
+impl Drop for ChildMonitor<'_> {
+    fn drop(&mut self) {
+        // Delete the sources from the poller and close the pidfd. If creating the monitor failed,
+        // some of them were never added, and deleting them fails harmlessly.
+        for &which in &[ChildEvent::Exited, ChildEvent::Stdout, ChildEvent::Stderr] {
+            if let Some(fd) = self.fd(which) {
+                let _ = self.poller.delete(fd);
+            }
+        }
+        let _ = syscall!(close(self.pidfd));
+    }
+}
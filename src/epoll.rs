@@ -17,11 +17,18 @@ pub struct Poller {
     event_fd: RawFd,
     /// File descriptor for the timerfd that produces timeouts.
     timer_fd: Option<RawFd>,
+    /// The error that prevented the timerfd from being created.
+    timer_error: Option<io::Error>,
 }
 
 impl Poller {
     /// Creates a new poller.
-    pub fn new() -> io::Result<Poller> {
+    ///
+    /// If `timer` is `false`, no timerfd is created and timeouts are rounded up to whole
+    /// milliseconds.
+    // Clippy mistakes libc's C types for the `core::ffi` ones, which are newer than the MSRV.
+    #[allow(clippy::incompatible_msrv)]
+    pub fn new(timer: bool) -> io::Result<Poller> {
         // Create an epoll instance.
         //
         // Use `epoll_create1` with `EPOLL_CLOEXEC`.
@@ -29,7 +36,7 @@ impl Poller {
             libc::SYS_epoll_create1,
             libc::EPOLL_CLOEXEC as libc::c_int
         ))
-        .map(|fd| fd as libc::c_int)
+        .and_then(fd_from_syscall)
         .or_else(|e| {
             match e.raw_os_error() {
                 Some(libc::ENOSYS) => {
@@ -49,18 +56,29 @@ impl Poller {
 
         // Set up eventfd and timerfd.
         let event_fd = syscall!(eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK))?;
-        let timer_fd = syscall!(syscall(
-            libc::SYS_timerfd_create,
-            libc::CLOCK_MONOTONIC as libc::c_int,
-            (libc::TFD_CLOEXEC | libc::TFD_NONBLOCK) as libc::c_int,
-        ))
-        .map(|fd| fd as libc::c_int)
-        .ok();
+        let (timer_fd, timer_error) = if timer {
+            match syscall!(syscall(
+                libc::SYS_timerfd_create,
+                libc::CLOCK_MONOTONIC as libc::c_int,
+                (libc::TFD_CLOEXEC | libc::TFD_NONBLOCK) as libc::c_int,
+            ))
+            .and_then(fd_from_syscall)
+            {
+                Ok(fd) => (Some(fd), None),
+                Err(e) => {
+                    log::trace!("new: falling back to millisecond timeouts: {}", e);
+                    (None, Some(e))
+                }
+            }
+        } else {
+            (None, None)
+        };
 
         let poller = Poller {
             epoll_fd,
             event_fd,
             timer_fd,
+            timer_error,
         };
 
         if let Some(timer_fd) = timer_fd {
@@ -85,6 +103,21 @@ impl Poller {
         Ok(poller)
     }
 
+    /// Returns the epoll file descriptor.
+    pub fn epoll_fd(&self) -> RawFd {
+        self.epoll_fd
+    }
+
+    /// Returns `true` if timeouts are set with a timerfd.
+    pub fn has_timer(&self) -> bool {
+        self.timer_fd.is_some()
+    }
+
+    /// Returns the error that prevented the timerfd from being created, if any.
+    pub fn timer_error(&self) -> Option<&io::Error> {
+        self.timer_error.as_ref()
+    }
+
     /// Adds a new file descriptor.
     pub fn add(&self, fd: RawFd, ev: Event) -> io::Result<()> {
         log::trace!("add: epoll_fd={}, fd={}, ev={:?}", self.epoll_fd, fd, ev);
@@ -104,6 +137,7 @@ impl Poller {
     }
 
     /// Waits for I/O events with an optional timeout.
+    #[allow(clippy::incompatible_msrv)]
     pub fn wait(&self, events: &mut Events, timeout: Option<Duration>) -> io::Result<()> {
         log::trace!("wait: epoll_fd={}, timeout={:?}", self.epoll_fd, timeout);
 
@@ -122,7 +156,7 @@ impl Poller {
 
             syscall!(syscall(
                 libc::SYS_timerfd_settime,
-                timer_fd as libc::c_int,
+                timer_fd,
                 0 as libc::c_int,
                 &new_val as *const libc::itimerspec,
                 ptr::null_mut() as *mut libc::itimerspec
@@ -189,16 +223,22 @@ impl Poller {
             self.event_fd
         );
 
+        self.notify_signal_safe();
+        Ok(())
+    }
+
+    /// Signals the notification with a single `write()`, which is async-signal-safe.
+    pub fn notify_signal_safe(&self) {
         let buf: [u8; 8] = 1u64.to_ne_bytes();
         let _ = syscall!(write(
             self.event_fd,
             buf.as_ptr() as *const libc::c_void,
             buf.len()
         ));
-        Ok(())
     }
 
     /// Passes arguments to `epoll_ctl`.
+    #[allow(clippy::incompatible_msrv)]
     fn ctl(&self, op: libc::c_int, fd: RawFd, ev: Option<Event>) -> io::Result<()> {
         let mut ev = ev.map(|ev| {
             let mut flags = libc::EPOLLONESHOT;
@@ -244,6 +284,24 @@ impl Drop for Poller {
     }
 }
 
+/// Converts the `c_long` returned by a raw `syscall()` into a file descriptor.
+#[allow(clippy::incompatible_msrv)]
+pub fn fd_from_syscall(res: libc::c_long) -> io::Result<RawFd> {
+    res.try_into().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "file descriptor returned by the kernel does not fit in `RawFd`",
+        )
+    })
+}
+
+/// File descriptors are passed to raw `syscall()`s as `c_int` without conversion, so `RawFd` must
+/// be the same type. This function only exists to fail compilation if that's not the case.
+#[allow(dead_code, clippy::incompatible_msrv)]
+fn raw_fd_is_c_int(fd: RawFd) -> libc::c_int {
+    fd
+}
+
 /// `timespec` value that equals zero.
 const TS_ZERO: libc::timespec = libc::timespec {
     tv_sec: 0,
@@ -251,15 +309,24 @@ const TS_ZERO: libc::timespec = libc::timespec {
 };
 
 /// Epoll flags for all possible readability events.
+#[allow(clippy::incompatible_msrv)]
 fn read_flags() -> libc::c_int {
     libc::EPOLLIN | libc::EPOLLRDHUP | libc::EPOLLHUP | libc::EPOLLERR | libc::EPOLLPRI
 }
 
 /// Epoll flags for all possible writability events.
+#[allow(clippy::incompatible_msrv)]
 fn write_flags() -> libc::c_int {
     libc::EPOLLOUT | libc::EPOLLHUP | libc::EPOLLERR
 }
 
+/// Returns the interest left armed after `ev` is delivered to a registration with `interest`.
+///
+/// Delivering any event disables the whole registration.
+pub fn disarm(interest: Event, _ev: Event) -> Event {
+    Event::none(interest.key)
+}
+
 /// A list of reported I/O events.
 pub struct Events {
     list: Box<[libc::epoll_event]>,
@@ -277,7 +344,13 @@ impl Events {
         Events { list, len }
     }
 
+    /// Returns `true` if the last wait filled the whole list.
+    pub fn is_full(&self) -> bool {
+        self.len == self.list.len()
+    }
+
     /// Iterates over I/O events.
+    #[allow(clippy::incompatible_msrv)]
     pub fn iter(&self) -> impl Iterator<Item = Event> + '_ {
         self.list[..self.len].iter().map(|ev| Event {
             key: ev.u64 as usize,
@@ -185,6 +185,20 @@ impl Drop for Poller {
     }
 }
 
+/// Returns the interest left armed after `ev` is delivered to a registration with `interest`.
+///
+/// Readability and writability are separate oneshot filters, so only the one that fired is
+/// disabled. Readable events always come from `EVFILT_READ`, even when they are also reported as
+/// writable on EOF.
+pub fn disarm(mut interest: Event, ev: Event) -> Event {
+    if ev.readable {
+        interest.readable = false;
+    } else if ev.writable {
+        interest.writable = false;
+    }
+    interest
+}
+
 /// A list of reported I/O events.
 pub struct Events {
     list: Box<[libc::kevent]>,
@@ -209,6 +223,11 @@ impl Events {
         Events { list, len }
     }
 
+    /// Returns `true` if the last wait filled the whole list.
+    pub fn is_full(&self) -> bool {
+        self.len == self.list.len()
+    }
+
     /// Iterates over I/O events.
     pub fn iter(&self) -> impl Iterator<Item = Event> + '_ {
         // On some platforms, closing the read end of a pipe wakes up writers, but the
@@ -52,11 +52,16 @@
 #![warn(missing_docs, missing_debug_implementations, rust_2018_idioms)]
 #![allow(clippy::useless_conversion, clippy::unnecessary_cast)]
 
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+#[cfg(unix)]
+use std::convert::TryInto;
 use std::fmt;
 use std::io;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Mutex;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
 use std::usize;
 
 use cfg_if::cfg_if;
@@ -78,12 +83,14 @@ cfg_if! {
     if #[cfg(any(target_os = "linux", target_os = "android"))] {
         mod epoll;
         use epoll as sys;
+        const BACKEND: Backend = Backend::Epoll;
     } else if #[cfg(any(
         target_os = "illumos",
         target_os = "solaris",
     ))] {
         mod port;
         use port as sys;
+        const BACKEND: Backend = Backend::EventPorts;
     } else if #[cfg(any(
         target_os = "macos",
         target_os = "ios",
@@ -94,6 +101,7 @@ cfg_if! {
     ))] {
         mod kqueue;
         use kqueue as sys;
+        const BACKEND: Backend = Backend::Kqueue;
     } else if #[cfg(any(
         target_os = "vxworks",
         target_os = "fuchsia",
@@ -101,25 +109,50 @@ cfg_if! {
     ))] {
         mod poll;
         use poll as sys;
+        const BACKEND: Backend = Backend::Poll;
     } else if #[cfg(target_os = "windows")] {
         mod wepoll;
         use wepoll as sys;
+        const BACKEND: Backend = Backend::Wepoll;
     } else {
         compile_error!("polling does not support this target OS");
     }
 }
 
+#[cfg(target_os = "linux")]
+mod child;
+#[cfg(target_os = "linux")]
+pub use child::{ChildEvent, ChildMonitor};
+
 /// Key associated with notifications.
 const NOTIFY_KEY: usize = std::usize::MAX;
 
+/// Maximum number of follow-up polls in `Poller::wait_exhaustive()`.
+const EXHAUSTIVE_ROUNDS: usize = 16;
+
+/// Number of events the native backends fetch from the operating system at a time.
+const BATCH_SIZE: usize = 1000;
+
 /// Indicates that a file descriptor or socket can read or write without blocking.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Event {
     /// Key identifying the file descriptor or socket.
+    ///
+    /// The key is passed to the operating system as-is, for example in the `data` field of an
+    /// `epoll_event` or the `udata` field of a `kevent`, and returned unchanged in delivered
+    /// events. This makes it suitable for storing a pointer to per-connection state, converted
+    /// with `as usize`, as long as the pointer stays valid while events can be delivered.
     pub key: usize,
     /// Can it do a read operation without blocking?
+    ///
+    /// Errors and hangups are reported as readability, so the next read returns them.
     pub readable: bool,
     /// Can it do a write operation without blocking?
+    ///
+    /// Errors and hangups are reported as writability, so the next write returns them. This also
+    /// happens for writable-only registrations, because the operating system reports errors
+    /// regardless of interest. To tell an error apart from genuine writability, check the
+    /// socket's pending error (`SO_ERROR`) or let the write fail.
     pub writable: bool,
 }
 
@@ -167,13 +200,333 @@ impl Event {
             writable: false,
         }
     }
+
+    /// Interprets the key of this event as a [`Token`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Token};
+    ///
+    /// let ev = Event::readable(Token::new(3, 1).into());
+    /// assert_eq!(ev.token(), Token::new(3, 1));
+    /// ```
+    #[cfg(target_pointer_width = "64")]
+    pub fn token(&self) -> Token {
+        Token::from(self.key)
+    }
+}
+
+/// A breakdown of a batch of events by kind of readiness.
+///
+/// # Examples
+///
+/// ```
+/// use polling::{Event, EventCounts, Poller};
+/// use std::time::Duration;
+///
+/// let poller = Poller::new()?;
+/// let mut events = Vec::new();
+/// poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+///
+/// let counts = EventCounts::new(&events);
+/// println!("{} read-bound, {} write-bound", counts.readable_only, counts.writable_only);
+///
+/// let counts = EventCounts::new(&[Event::readable(1), Event::all(2), Event::all(3)]);
+/// assert_eq!(counts.readable_only, 1);
+/// assert_eq!(counts.both, 2);
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EventCounts {
+    /// Number of events that are readable but not writable.
+    pub readable_only: usize,
+    /// Number of events that are writable but not readable.
+    pub writable_only: usize,
+    /// Number of events that are both readable and writable.
+    pub both: usize,
+    /// Number of events that are neither readable nor writable.
+    pub neither: usize,
+}
+
+impl EventCounts {
+    /// Counts the events in a batch.
+    pub fn new(events: &[Event]) -> EventCounts {
+        let mut counts = EventCounts::default();
+        for ev in events {
+            match (ev.readable, ev.writable) {
+                (true, false) => counts.readable_only += 1,
+                (false, true) => counts.writable_only += 1,
+                (true, true) => counts.both += 1,
+                (false, false) => counts.neither += 1,
+            }
+        }
+        counts
+    }
+}
+
+/// A key made of a slot index and a generation counter.
+///
+/// File descriptor numbers are reused by the operating system as soon as they are closed, so an
+/// event that was already in flight for an old file descriptor can show up after a new one was
+/// registered in its place. Bumping the generation every time a slot is reused lets the caller
+/// detect and drop such stale events.
+///
+/// A token packs into the `usize` key of an [`Event`], with the generation in the upper 32 bits
+/// and the index in the lower 32 bits. Because of this it is only available on 64-bit targets.
+///
+/// Note that `Token::new(u32::MAX, u32::MAX)` packs into `usize::MAX`, which is reserved for
+/// internal use and rejected by [`Poller::add()`] and [`Poller::modify()`].
+///
+/// # Examples
+///
+/// ```
+/// use polling::{Event, Poller, Token};
+///
+/// let socket = std::net::TcpListener::bind("127.0.0.1:0")?;
+/// socket.set_nonblocking(true)?;
+/// let token = Token::new(0, 1);
+///
+/// let poller = Poller::new()?;
+/// poller.add(&socket, Event::readable(token.into()))?;
+///
+/// let mut events = Vec::new();
+/// poller.wait(&mut events, Some(std::time::Duration::from_secs(0)))?;
+///
+/// for ev in &events {
+///     if ev.token().generation() != token.generation() {
+///         // A stale event from a previous registration in the same slot.
+///         continue;
+///     }
+/// }
+/// # std::io::Result::Ok(())
+/// ```
+#[cfg(target_pointer_width = "64")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Token {
+    index: u32,
+    generation: u32,
+}
+
+#[cfg(target_pointer_width = "64")]
+impl Token {
+    /// Creates a token from a slot index and a generation counter.
+    pub fn new(index: u32, generation: u32) -> Token {
+        Token { index, generation }
+    }
+
+    /// Returns the slot index.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the generation counter.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl From<Token> for usize {
+    fn from(token: Token) -> usize {
+        ((token.generation as usize) << 32) | token.index as usize
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl From<usize> for Token {
+    fn from(key: usize) -> Token {
+        Token {
+            index: key as u32,
+            generation: (key >> 32) as u32,
+        }
+    }
 }
 
 /// Waits for I/O events.
 pub struct Poller {
-    poller: sys::Poller,
+    poller: Arc<sys::Poller>,
+    software_timer: Option<SoftwareTimer>,
     events: Mutex<sys::Events>,
     notified: AtomicBool,
+    notify_key: Option<usize>,
+    user_notified: AtomicBool,
+    draining: AtomicBool,
+    timers: Mutex<BinaryHeap<Reverse<(Instant, usize)>>>,
+    /// The number of armed timers, to skip locking `timers` when there are none.
+    armed_timers: AtomicUsize,
+    /// Whether sources are recorded in `registrations`, see `Builder::track_registrations()`.
+    tracking: bool,
+    registrations: Mutex<Registrations>,
+    last_wake: Mutex<Option<Instant>>,
+    last_full: AtomicBool,
+    commits: AtomicUsize,
+    rearms: Mutex<Vec<(Instant, RawSource, Event)>>,
+    handlers: Mutex<HashMap<RawSource, Handler>>,
+    #[cfg(feature = "test-util")]
+    clock: Option<MockClock>,
+    #[cfg(feature = "metrics")]
+    wait_hook: Mutex<Option<WaitHook>>,
+}
+
+/// A callback that receives how long each wait blocked, see `Poller::set_wait_hook()`.
+#[cfg(feature = "metrics")]
+type WaitHook = Arc<dyn Fn(Duration) + Send + Sync>;
+
+/// A callback for the events of a source, see `Poller::add_with_handler()`.
+type Handler = Box<dyn FnMut(Event) + Send>;
+
+/// A clock that only moves when told to, for testing timers.
+///
+/// A [`Poller`] created with [`Poller::with_mock_clock()`] uses this clock instead of
+/// [`Instant::now()`] to decide when timers armed with [`Poller::arm_timer()`] expire and how long
+/// [`Poller::wait()`] may block before the nearest one does. Waiting on I/O itself, and timeouts
+/// passed to [`Poller::wait()`], still use the operating system's clock.
+///
+/// This type is only available with the `test-util` feature.
+///
+/// # Examples
+///
+/// ```
+/// use polling::{Event, MockClock, Poller};
+/// use std::time::Duration;
+///
+/// let clock = MockClock::new();
+/// let poller = Poller::with_mock_clock(clock.clone())?;
+/// poller.arm_timer(7, clock.now() + Duration::from_secs(60))?;
+///
+/// let mut events = Vec::new();
+/// poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+/// assert!(events.is_empty());
+///
+/// clock.advance(Duration::from_secs(60));
+/// poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+/// assert_eq!(events, [Event::readable(7)]);
+/// # std::io::Result::Ok(())
+/// ```
+#[cfg(feature = "test-util")]
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: std::sync::Arc<Mutex<Instant>>,
+}
+
+#[cfg(feature = "test-util")]
+impl MockClock {
+    /// Creates a clock starting at the current instant.
+    pub fn new() -> MockClock {
+        MockClock {
+            now: std::sync::Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Returns the current instant of this clock.
+    pub fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+
+    /// Moves this clock forward.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl Default for MockClock {
+    fn default() -> MockClock {
+        MockClock::new()
+    }
+}
+
+/// Bookkeeping for all registered file descriptors or sockets.
+#[derive(Debug, Default)]
+struct Registrations {
+    /// Registrations by file descriptor or socket.
+    sources: HashMap<RawSource, Entry>,
+    /// File descriptors or sockets by the key they are registered with.
+    ///
+    /// If several are registered with the same key, only the most recent one is indexed.
+    keys: HashMap<usize, RawSource>,
+}
+
+impl Registrations {
+    /// Returns the registration of a file descriptor or socket.
+    fn get(&self, raw: RawSource) -> Option<&Entry> {
+        self.sources.get(&raw)
+    }
+
+    /// Returns the registration with the given key.
+    fn get_by_key(&self, key: usize) -> Option<&Entry> {
+        let raw = self.keys.get(&key)?;
+        self.sources.get(raw)
+    }
+
+    /// Returns the registration with the given key.
+    fn get_by_key_mut(&mut self, key: usize) -> Option<&mut Entry> {
+        let raw = self.keys.get(&key)?;
+        self.sources.get_mut(raw)
+    }
+
+    /// Records a new registration.
+    ///
+    /// A stale registration of the same file descriptor or socket, left behind when it was closed
+    /// without being deleted, is replaced.
+    fn insert(&mut self, raw: RawSource, registration: Entry) {
+        if let Some(old) = self.sources.insert(raw, registration) {
+            self.unindex(raw, old.interest.key);
+        }
+        self.keys.insert(registration.interest.key, raw);
+    }
+
+    /// Updates the interest of an existing registration.
+    fn update(&mut self, raw: RawSource, interest: Event) -> Option<&Entry> {
+        let old_key = self.sources.get(&raw)?.interest.key;
+        if old_key != interest.key {
+            self.unindex(raw, old_key);
+            self.keys.insert(interest.key, raw);
+        }
+
+        let registration = self.sources.get_mut(&raw)?;
+        registration.registered = interest;
+        registration.interest = interest;
+        Some(registration)
+    }
+
+    /// Removes a registration.
+    fn remove(&mut self, raw: RawSource) -> Option<Entry> {
+        let registration = self.sources.remove(&raw)?;
+        self.unindex(raw, registration.interest.key);
+        Some(registration)
+    }
+
+    /// Removes `key` from the index if it points at `raw`.
+    fn unindex(&mut self, raw: RawSource, key: usize) {
+        if self.keys.get(&key) == Some(&raw) {
+            self.keys.remove(&key);
+        }
+    }
+}
+
+/// Bookkeeping for a registered file descriptor or socket.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    /// The interest it was last registered with.
+    registered: Event,
+    /// The interest that is currently armed.
+    ///
+    /// This is `registered` minus the kinds of events that have been delivered since, which the
+    /// operating system disables in oneshot mode.
+    interest: Event,
+    /// A human-readable label for log messages.
+    label: Option<&'static str>,
+    /// The tier its events are processed in.
+    tier: usize,
+    /// The minimum time between two of its events, see `Poller::add_with_rate_limit()`.
+    cooldown: Option<Duration>,
+    /// When its last event was delivered, if it is rate-limited.
+    last_reported: Option<Instant>,
+    /// Whether it wants to write, if writable interest is managed by the poller, see
+    /// `Poller::add_writable_on_demand()`.
+    wants_write: Option<bool>,
 }
 
 impl Poller {
@@ -188,11 +541,68 @@ impl Poller {
     /// # std::io::Result::Ok(())
     /// ```
     pub fn new() -> io::Result<Poller> {
-        Ok(Poller {
-            poller: sys::Poller::new()?,
-            events: Mutex::new(sys::Events::new()),
-            notified: AtomicBool::new(false),
-        })
+        Builder::new().build()
+    }
+
+    /// Returns a [`Builder`] for configuring a new poller.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::Poller;
+    ///
+    /// let poller = Poller::builder().without_timer().build()?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn builder() -> Builder {
+        Builder::new()
+    }
+
+    /// Creates a new poller on the given backend.
+    ///
+    /// This is a shorthand for `Poller::builder().backend(backend).build()`.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if `backend` isn't compiled in, see [`Backend::available()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Backend, Poller};
+    ///
+    /// #[cfg(target_os = "linux")]
+    /// assert!(Poller::with_backend(Backend::Kqueue).is_err());
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn with_backend(backend: Backend) -> io::Result<Poller> {
+        Builder::new().backend(backend).build()
+    }
+
+    /// Returns the backend this poller is built on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Backend, Poller};
+    ///
+    /// let poller = Poller::new()?;
+    /// #[cfg(target_os = "linux")]
+    /// assert_eq!(poller.backend(), Backend::Epoll);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn backend(&self) -> Backend {
+        BACKEND
+    }
+
+    /// Creates a new poller whose timers follow a [`MockClock`].
+    ///
+    /// This method is only available with the `test-util` feature.
+    #[cfg(feature = "test-util")]
+    pub fn with_mock_clock(clock: MockClock) -> io::Result<Poller> {
+        let mut poller = Poller::new()?;
+        poller.clock = Some(clock);
+        Ok(poller)
     }
 
     /// Adds a file descriptor or socket to the poller.
@@ -214,13 +624,16 @@ impl Poller {
     /// the next event of the same kind.
     ///
     /// Don't forget to [`delete()`][`Poller::delete()`] the file descriptor or socket when it is
-    /// no longer used!
+    /// no longer used! Use [`register()`][`Poller::register()`] instead to have that done
+    /// automatically.
     ///
     /// # Errors
     ///
     /// This method returns an error in the following situations:
     ///
     /// * If `key` equals `usize::MAX` because that key is reserved for internal use.
+    /// * If `key` is the one notifications are reported with, see
+    ///   [`Builder::notify_as_event()`].
     /// * If an error is returned by the syscall.
     ///
     /// # Examples
@@ -239,186 +652,1953 @@ impl Poller {
     /// # std::io::Result::Ok(())
     /// ```
     pub fn add(&self, source: impl Source, interest: Event) -> io::Result<()> {
-        if interest.key == NOTIFY_KEY {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "the key is not allowed to be `usize::MAX`",
-            ));
-        }
-        self.poller.add(source.raw(), interest)
+        self.add_registration(source.raw(), interest, None)
     }
 
-    /// Modifies the interest in a file descriptor or socket.
-    ///
-    /// This method has the same behavior as [`add()`][`Poller::add()`] except it modifies the
-    /// interest of a previously added file descriptor or socket.
+    /// Adds a file descriptor or socket to the poller, along with a human-readable label.
     ///
-    /// To use this method with a file descriptor or socket, you must first add it using
-    /// [`add()`][`Poller::add()`].
-    ///
-    /// Note that interest in I/O events needs to be re-enabled using
-    /// [`modify()`][`Poller::modify()`] again after an event is delivered if we're interested in
-    /// the next event of the same kind.
+    /// This method has the same behavior as [`add()`][`Poller::add()`], except that the label is
+    /// included in log messages about this file descriptor or socket, and can be retrieved with
+    /// [`label()`][`Poller::label()`] until it is [`delete()`][`Poller::delete()`]d.
     ///
     /// # Errors
     ///
-    /// This method returns an error in the following situations:
-    ///
-    /// * If `key` equals `usize::MAX` because that key is reserved for internal use.
-    /// * If an error is returned by the syscall.
+    /// This method fails if the poller doesn't track registrations, see
+    /// [`Builder::track_registrations()`], or in the same situations as [`add()`][`Poller::add()`].
     ///
     /// # Examples
     ///
-    /// To enable interest in all events:
-    ///
-    /// ```no_run
-    /// # use polling::{Event, Poller};
-    /// # let source = std::net::TcpListener::bind("127.0.0.1:0")?;
-    /// # let key = 7;
-    /// # let poller = Poller::new()?;
-    /// # poller.add(&source, Event::none(key))?;
-    /// poller.modify(&source, Event::all(key))?;
-    /// # std::io::Result::Ok(())
     /// ```
+    /// use polling::{Event, Poller};
     ///
-    /// To enable interest in readable events and disable interest in writable events:
+    /// let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// source.set_nonblocking(true)?;
     ///
-    /// ```no_run
-    /// # use polling::{Event, Poller};
-    /// # let source = std::net::TcpListener::bind("127.0.0.1:0")?;
-    /// # let key = 7;
-    /// # let poller = Poller::new()?;
-    /// # poller.add(&source, Event::none(key))?;
-    /// poller.modify(&source, Event::readable(key))?;
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// poller.add_with_label(&source, Event::readable(7), "listener")?;
+    /// assert_eq!(poller.label(&source), Some("listener"));
     /// # std::io::Result::Ok(())
     /// ```
+    pub fn add_with_label(
+        &self,
+        source: impl Source,
+        interest: Event,
+        label: &'static str,
+    ) -> io::Result<()> {
+        self.require_tracking("adding a source with a label")?;
+        self.add_registration(source.raw(), interest, Some(label))
+    }
+
+    /// Returns the label a file descriptor or socket was added with.
     ///
-    /// To disable interest in readable events and enable interest in writable events:
+    /// Returns `None` if it was added without a label or isn't in the poller.
+    pub fn label(&self, source: impl Source) -> Option<&'static str> {
+        let registrations = self.registrations.lock().unwrap();
+        registrations
+            .get(source.raw())
+            .and_then(|registration| registration.label)
+    }
+
+    /// Returns `true` if a file descriptor or socket has been added to the poller.
+    ///
+    /// Always returns `false` if the poller doesn't track registrations, see
+    /// [`Builder::track_registrations()`].
+    ///
+    /// # Examples
     ///
-    /// ```no_run
-    /// # use polling::{Event, Poller};
-    /// # let poller = Poller::new()?;
-    /// # let key = 7;
-    /// # let source = std::net::TcpListener::bind("127.0.0.1:0")?;
-    /// # poller.add(&source, Event::none(key))?;
-    /// poller.modify(&source, Event::writable(key))?;
-    /// # std::io::Result::Ok(())
     /// ```
+    /// use polling::{Event, Poller};
     ///
-    /// To disable interest in all events:
+    /// let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// source.set_nonblocking(true)?;
     ///
-    /// ```no_run
-    /// # use polling::{Event, Poller};
-    /// # let source = std::net::TcpListener::bind("127.0.0.1:0")?;
-    /// # let key = 7;
-    /// # let poller = Poller::new()?;
-    /// # poller.add(&source, Event::none(key))?;
-    /// poller.modify(&source, Event::none(key))?;
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// poller.add(&source, Event::readable(7))?;
+    /// assert!(poller.contains(&source));
+    ///
+    /// poller.delete(&source)?;
+    /// assert!(!poller.contains(&source));
     /// # std::io::Result::Ok(())
     /// ```
-    pub fn modify(&self, source: impl Source, interest: Event) -> io::Result<()> {
-        if interest.key == NOTIFY_KEY {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "the key is not allowed to be `usize::MAX`",
-            ));
-        }
-        self.poller.modify(source.raw(), interest)
+    pub fn contains(&self, source: impl Source) -> bool {
+        let registrations = self.registrations.lock().unwrap();
+        registrations.get(source.raw()).is_some()
     }
 
-    /// Removes a file descriptor or socket from the poller.
+    /// Returns the number of epoll watches this poller holds.
     ///
-    /// Unlike [`add()`][`Poller::add()`], this method only removes the file descriptor or
-    /// socket from the poller without putting it back into blocking mode.
+    /// This is the number of file descriptors and sockets in the poller, plus one or two for
+    /// the eventfd and timerfd the poller uses internally. The limit in
+    /// [`epoll_max_user_watches()`] applies to the sum over all pollers of the same user, in this
+    /// and other processes, so the headroom computed from a single poller is an upper bound.
+    /// If the poller doesn't track registrations, see [`Builder::track_registrations()`], the
+    /// watches are counted from `/proc` instead.
+    ///
+    /// This method is only available on Linux and Android.
     ///
     /// # Examples
     ///
     /// ```
     /// use polling::{Event, Poller};
-    /// use std::net::TcpListener;
     ///
-    /// let socket = TcpListener::bind("127.0.0.1:0")?;
-    /// socket.set_nonblocking(true)?;
-    /// let key = 7;
+    /// let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// let before = poller.epoll_watches();
     ///
-    /// let poller = Poller::new()?;
-    /// poller.add(&socket, Event::all(key))?;
-    /// poller.delete(&socket)?;
+    /// poller.add(&source, Event::readable(7))?;
+    /// assert_eq!(poller.epoll_watches(), before + 1);
+    /// # poller.delete(&source)?;
     /// # std::io::Result::Ok(())
     /// ```
-    pub fn delete(&self, source: impl Source) -> io::Result<()> {
-        self.poller.delete(source.raw())
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn epoll_watches(&self) -> usize {
+        let internal = if self.poller.has_timer() { 2 } else { 1 };
+        if self.tracking() {
+            return self.registrations.lock().unwrap().sources.len() + internal;
+        }
+
+        // The kernel lists one `tfd:` line per watch in the epoll fd's info.
+        let path = format!("/proc/self/fdinfo/{}", self.poller.epoll_fd());
+        match std::fs::read_to_string(path) {
+            Ok(info) => info.lines().filter(|line| line.starts_with("tfd:")).count(),
+            Err(e) => {
+                log::trace!("epoll_watches: can't read the epoll fd's info: {}", e);
+                internal
+            }
+        }
     }
 
-    /// Waits for at least one I/O event and returns the number of new events.
-    ///
-    /// New events will be appended to `events`. If necessary, make sure to clear the [`Vec`]
-    /// before calling [`wait()`][`Poller::wait()`]!
-    ///
-    /// This method will return with no new events if a notification is delivered by the
-    /// [`notify()`] method, or the timeout is reached. Sometimes it may even return with no events
-    /// spuriously.
-    ///
-    /// Only one thread can wait on I/O. If another thread is already in [`wait()`], concurrent
-    /// calls to this method will return immediately with no new events.
-    ///
-    /// If the operating system is ready to deliver a large number of events at once, this method
-    /// may decide to deliver them in smaller batches.
+    /// Returns a capacity for the events buffer passed to [`wait()`][`Poller::wait()`].
     ///
-    /// [`notify()`]: `Poller::notify()`
-    /// [`wait()`]: `Poller::wait()`
+    /// This is the number of file descriptors and sockets in the poller plus the number of armed
+    /// timers, which is the most events a single call can deliver, capped at the number of events
+    /// the poller fetches from the operating system at a time. It changes as sources are added
+    /// and deleted, so a long-running event loop can check it periodically and grow its buffer
+    /// with [`Vec::reserve()`]. If the poller doesn't track registrations, see
+    /// [`Builder::track_registrations()`], sources can't be counted and this is the cap.
     ///
     /// # Examples
     ///
     /// ```
     /// use polling::{Event, Poller};
-    /// use std::net::TcpListener;
-    /// use std::time::Duration;
     ///
-    /// let socket = TcpListener::bind("127.0.0.1:0")?;
-    /// socket.set_nonblocking(true)?;
-    /// let key = 7;
+    /// let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// source.set_nonblocking(true)?;
     ///
-    /// let poller = Poller::new()?;
-    /// poller.add(&socket, Event::all(key))?;
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// poller.add(&source, Event::readable(7))?;
     ///
-    /// let mut events = Vec::new();
-    /// let n = poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    /// let mut events = Vec::with_capacity(poller.recommended_events_capacity());
+    /// assert!(events.capacity() >= 1);
+    /// # poller.wait(&mut events, Some(std::time::Duration::from_secs(0)))?;
     /// # std::io::Result::Ok(())
     /// ```
-    pub fn wait(&self, events: &mut Vec<Event>, timeout: Option<Duration>) -> io::Result<usize> {
-        log::trace!("Poller::wait(_, {:?})", timeout);
-
-        if let Ok(mut lock) = self.events.try_lock() {
-            // Wait for I/O events.
-            self.poller.wait(&mut lock, timeout)?;
-
-            // Clear the notification, if any.
-            self.notified.swap(false, Ordering::SeqCst);
-
-            // Collect events.
-            let len = events.len();
-            events.extend(lock.iter().filter(|ev| ev.key != usize::MAX));
-            Ok(events.len() - len)
-        } else {
-            log::trace!("wait: skipping because another thread is already waiting on I/O");
-            Ok(0)
+    pub fn recommended_events_capacity(&self) -> usize {
+        if !self.tracking() {
+            return BATCH_SIZE;
         }
+        let sources = self.registrations.lock().unwrap().sources.len();
+        let timers = self.timers.lock().unwrap().len();
+        (sources + timers).min(BATCH_SIZE)
     }
 
-    /// Wakes up the current or the following invocation of [`wait()`].
+    /// Returns the interest that is currently armed for a file descriptor or socket.
     ///
-    /// If no thread is calling [`wait()`] right now, this method will cause the following call
-    /// to wake up immediately.
+    /// Because polling is done in oneshot mode, interest in a kind of event is disabled once such
+    /// an event is delivered by [`wait()`][`Poller::wait()`], and this method reports it as such
+    /// until [`modify()`][`Poller::modify()`] re-enables it. Depending on the platform, delivering
+    /// one kind of event may disable interest in the other kind as well.
     ///
-    /// [`wait()`]: `Poller::wait()`
+    /// This relies on keys being unique: if several file descriptors or sockets share a key,
+    /// delivered events are only accounted for on the one that was registered last.
+    ///
+    /// Returns `None` if the file descriptor or socket isn't in the poller, or if the poller
+    /// doesn't track registrations, see [`Builder::track_registrations()`].
     ///
     /// # Examples
     ///
     /// ```
-    /// use polling::Poller;
+    /// use polling::{Event, Poller};
     ///
-    /// let poller = Poller::new()?;
+    /// let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// source.set_nonblocking(true)?;
+    ///
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// poller.add(&source, Event::readable(7))?;
+    /// assert_eq!(poller.interest(&source), Some(Event::readable(7)));
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn interest(&self, source: impl Source) -> Option<Event> {
+        let registrations = self.registrations.lock().unwrap();
+        registrations
+            .get(source.raw())
+            .map(|registration| registration.interest)
+    }
+
+    /// Returns a snapshot of all file descriptors and sockets in the poller, sorted by handle.
+    ///
+    /// This is meant for debugging, for example to dump the state of a misbehaving event loop.
+    /// The snapshot is empty if the poller doesn't track registrations, see
+    /// [`Builder::track_registrations()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    ///
+    /// let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// source.set_nonblocking(true)?;
+    ///
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// poller.add_with_label(&source, Event::readable(7), "listener")?;
+    ///
+    /// for info in poller.dump_registrations() {
+    ///     println!("{:?}", info);
+    /// }
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn dump_registrations(&self) -> Vec<RegistrationInfo> {
+        let registrations = self.registrations.lock().unwrap();
+        let mut infos = registrations
+            .sources
+            .iter()
+            .map(|(&source, registration)| RegistrationInfo {
+                source,
+                registered: registration.registered,
+                armed: registration.interest,
+                label: registration.label,
+                tier: registration.tier,
+            })
+            .collect::<Vec<_>>();
+        infos.sort_by_key(|info| info.source);
+        infos
+    }
+
+    /// Assigns a file descriptor or socket to a tier.
+    ///
+    /// Tiers partition the events delivered by a single [`wait()`][`Poller::wait()`] so they can
+    /// be processed in order of importance, with [`iter_tier()`][`Poller::iter_tier()`]. The
+    /// operating system knows nothing about tiers, so this doesn't change which events are
+    /// delivered or when. Everything starts in tier 0.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the file descriptor or socket isn't in the poller, or if
+    /// the poller doesn't track registrations, see [`Builder::track_registrations()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    ///
+    /// let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// source.set_nonblocking(true)?;
+    ///
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// poller.add(&source, Event::readable(7))?;
+    /// poller.set_tier(&source, 2)?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn set_tier(&self, source: impl Source, tier: usize) -> io::Result<()> {
+        let mut registrations = self.registrations.lock().unwrap();
+        let registration = registrations
+            .sources
+            .get_mut(&source.raw())
+            .ok_or(io::ErrorKind::NotFound)?;
+        registration.tier = tier;
+        Ok(())
+    }
+
+    /// Iterates over the events in `events` whose file descriptor or socket is in `tier`.
+    ///
+    /// Tiers are looked up by key, so this relies on keys being unique. Events with a key that
+    /// isn't registered, such as expired timers, are considered to be in tier 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::Poller;
+    /// use std::time::Duration;
+    ///
+    /// let poller = Poller::new()?;
+    ///
+    /// let mut events = Vec::new();
+    /// poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    ///
+    /// for tier in 0..3 {
+    ///     for ev in poller.iter_tier(&events, tier) {
+    ///         // Handle the event.
+    ///     }
+    /// }
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn iter_tier<'a>(
+        &'a self,
+        events: &'a [Event],
+        tier: usize,
+    ) -> impl Iterator<Item = Event> + 'a {
+        events.iter().cloned().filter(move |ev| {
+            let registrations = self.registrations.lock().unwrap();
+            let ev_tier = registrations
+                .get_by_key(ev.key)
+                .map_or(0, |registration| registration.tier);
+            ev_tier == tier
+        })
+    }
+
+    /// Returns the number of distinct file descriptors, sockets, and timers that `events` are for.
+    ///
+    /// The number of events can be larger than the number of ready sources: with kqueue, a socket
+    /// that is both readable and writable may be reported as two separate events, and
+    /// [`wait()`][`Poller::wait()`] calls accumulated into the same list can report a source
+    /// several times. This maps keys to file descriptors and sockets with the bookkeeping, so it
+    /// relies on keys being unique. Events with a key that isn't registered or tracked, such as
+    /// expired timers, are counted once per key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    ///
+    /// let poller = Poller::new()?;
+    /// let events = [Event::readable(1), Event::writable(1), Event::readable(2)];
+    /// assert_eq!(poller.distinct_sources(&events), 2);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn distinct_sources(&self, events: &[Event]) -> usize {
+        let registrations = self.registrations.lock().unwrap();
+        let mut sources = HashSet::new();
+        let mut others = HashSet::new();
+        for ev in events {
+            match registrations.keys.get(&ev.key) {
+                Some(&raw) => sources.insert(raw),
+                None => others.insert(ev.key),
+            };
+        }
+        sources.len() + others.len()
+    }
+
+    /// Adds a socket to the poller after setting its receive and send low-water marks.
+    ///
+    /// A socket with a receive low-water mark (`SO_RCVLOWAT`) of `n` bytes is only reported as
+    /// readable once at least `n` bytes are queued, or on EOF and errors, which batches small
+    /// messages into fewer wakeups. The send low-water mark (`SO_SNDLOWAT`) similarly defers
+    /// writability until that much buffer space is free. `None` leaves an option unchanged.
+    ///
+    /// The options stay set on the socket after it is deleted from the poller. Note that a read
+    /// that leaves fewer than `n` bytes queued means no further readable event is delivered until
+    /// more data arrives, so the caller should keep reading until the socket would block. Linux
+    /// doesn't support changing `SO_SNDLOWAT`, so passing `Some` for it makes this method fail
+    /// there.
+    ///
+    /// This method is only available on Unix platforms.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if a low-water mark doesn't fit in a `c_int`, if setting
+    /// either option fails, or in the same situations as [`add()`][`Poller::add()`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use polling::{Event, Poller};
+    /// use std::net::TcpStream;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8000")?;
+    /// stream.set_nonblocking(true)?;
+    ///
+    /// // Only wake up once a 16-byte header has arrived.
+    /// let poller = Poller::new()?;
+    /// poller.add_with_lowat(&stream, Event::readable(7), Some(16), None)?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    #[cfg(unix)]
+    pub fn add_with_lowat(
+        &self,
+        source: impl Source,
+        interest: Event,
+        recv_lowat: Option<usize>,
+        send_lowat: Option<usize>,
+    ) -> io::Result<()> {
+        let raw = source.raw();
+        if let Some(lowat) = recv_lowat {
+            set_lowat(raw, libc::SO_RCVLOWAT, lowat)?;
+        }
+        if let Some(lowat) = send_lowat {
+            set_lowat(raw, libc::SO_SNDLOWAT, lowat)?;
+        }
+        self.add(raw, interest)
+    }
+
+    /// Adds a file descriptor or socket whose writable interest is managed by the poller.
+    ///
+    /// A socket almost always has room in its send buffer, so enabling writable interest when
+    /// there is nothing to write just causes useless wakeups. For a source added with this method,
+    /// the poller enables writable interest only while the caller has data to write: after
+    /// [`want_write()`][`Poller::want_write()`] and until
+    /// [`write_drained()`][`Poller::write_drained()`]. The writable part of the interest passed
+    /// to [`modify()`][`Poller::modify()`] is ignored and replaced with whether the source wants
+    /// to write, so re-enabling interest after an event is just
+    /// `poller.modify(&source, Event::readable(key))`.
+    ///
+    /// The source starts with readable interest and not wanting to write.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the poller doesn't track registrations, see
+    /// [`Builder::track_registrations()`], or in the same situations as [`add()`][`Poller::add()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    ///
+    /// let source = std::net::UdpSocket::bind("127.0.0.1:0")?;
+    /// source.set_nonblocking(true)?;
+    ///
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// poller.add_writable_on_demand(&source, 7)?;
+    ///
+    /// // A message is queued but couldn't be sent yet.
+    /// poller.want_write(&source)?;
+    /// assert_eq!(poller.interest(&source), Some(Event::all(7)));
+    ///
+    /// // ... on the writable event, send until there's nothing left, then:
+    /// poller.write_drained(&source)?;
+    /// assert_eq!(poller.interest(&source), Some(Event::readable(7)));
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn add_writable_on_demand(&self, source: impl Source, key: usize) -> io::Result<()> {
+        let raw = source.raw();
+        self.require_tracking("adding a source with writable interest on demand")?;
+        self.add_registration(raw, Event::readable(key), None)?;
+        if let Some(registration) = self.registrations.lock().unwrap().sources.get_mut(&raw) {
+            registration.wants_write = Some(false);
+        }
+        Ok(())
+    }
+
+    /// Marks a file descriptor or socket as having data to write, enabling writable interest.
+    ///
+    /// Interest in readable events is left as it is. See
+    /// [`add_writable_on_demand()`][`Poller::add_writable_on_demand()`].
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the source isn't in the poller or wasn't added with
+    /// [`add_writable_on_demand()`][`Poller::add_writable_on_demand()`].
+    pub fn want_write(&self, source: impl Source) -> io::Result<()> {
+        self.set_wants_write(source.raw(), true)
+    }
+
+    /// Marks a file descriptor or socket as having nothing left to write, disabling writable
+    /// interest.
+    ///
+    /// Interest in readable events is left as it is. See
+    /// [`add_writable_on_demand()`][`Poller::add_writable_on_demand()`].
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the source isn't in the poller or wasn't added with
+    /// [`add_writable_on_demand()`][`Poller::add_writable_on_demand()`].
+    pub fn write_drained(&self, source: impl Source) -> io::Result<()> {
+        self.set_wants_write(source.raw(), false)
+    }
+
+    fn set_wants_write(&self, raw: RawSource, wants_write: bool) -> io::Result<()> {
+        let interest = {
+            let mut registrations = self.registrations.lock().unwrap();
+            let registration = registrations
+                .sources
+                .get_mut(&raw)
+                .ok_or(io::ErrorKind::NotFound)?;
+            if registration.wants_write.is_none() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "writable interest is not managed by the poller for this source",
+                ));
+            }
+            registration.wants_write = Some(wants_write);
+            if registration.interest.writable == wants_write {
+                return Ok(());
+            }
+            registration.interest
+        };
+        self.modify(raw, interest)
+    }
+
+    /// Adds a listening socket that is only reported readable once a connection has data.
+    ///
+    /// With `defer_accept` set, the operating system holds back connections until the client
+    /// sends data, or until about `defer_accept` has passed, so [`accept()`] doesn't return idle
+    /// connections that would need to be polled again right away. On Linux and Android this sets
+    /// the `TCP_DEFER_ACCEPT` option, rounding the duration up to whole seconds. The kernel
+    /// measures the timeout in SYN-ACK retransmissions, so the actual delay can be longer. The
+    /// option stays set on the socket after it is deleted from the poller.
+    ///
+    /// On other platforms, connections can't be deferred this way, and `defer_accept` is
+    /// ignored: the listener is added like with [`add()`][`Poller::add()`], and reported as soon
+    /// as a connection is established.
+    ///
+    /// The listener is added with interest in readability under `key`.
+    ///
+    /// [`accept()`]: `std::net::TcpListener::accept()`
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the option can't be set, or in the same situations as
+    /// [`add()`][`Poller::add()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::Poller;
+    /// use std::net::TcpListener;
+    /// use std::time::Duration;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0")?;
+    /// listener.set_nonblocking(true)?;
+    ///
+    /// let poller = Poller::new()?;
+    /// poller.add_listener(&listener, 7, Some(Duration::from_secs(5)))?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    #[allow(clippy::incompatible_msrv)]
+    pub fn add_listener(
+        &self,
+        source: impl Source,
+        key: usize,
+        defer_accept: Option<Duration>,
+    ) -> io::Result<()> {
+        let raw = source.raw();
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            if let Some(timeout) = defer_accept {
+                let mut secs = timeout.as_secs();
+                if timeout.subsec_nanos() > 0 {
+                    secs += 1;
+                }
+                let secs = secs.min(libc::c_int::max_value() as u64) as libc::c_int;
+                syscall!(setsockopt(
+                    raw,
+                    libc::IPPROTO_TCP,
+                    libc::TCP_DEFER_ACCEPT,
+                    &secs as *const libc::c_int as *const libc::c_void,
+                    std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+                ))?;
+            }
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        {
+            if defer_accept.is_some() {
+                log::trace!("add_listener: deferred accept is not supported on this platform");
+            }
+        }
+        self.add(raw, Event::readable(key))
+    }
+
+    /// Puts a file descriptor or socket in nonblocking mode and adds it to the poller.
+    ///
+    /// Reading or writing in blocking mode after an event is delivered can still block, for
+    /// example when another thread drained the data in the meantime, which stalls the whole
+    /// event loop. This method prevents forgetting to set nonblocking mode by setting the
+    /// `O_NONBLOCK` flag with `fcntl()` before calling [`add()`][`Poller::add()`].
+    ///
+    /// The flag is part of the open file description, so it stays set after the file descriptor
+    /// is deleted from the poller, and it also applies to duplicates of the file descriptor,
+    /// including ones in other processes.
+    ///
+    /// This method is only available on Unix platforms.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the flag can't be set, or in the same situations as
+    /// [`add()`][`Poller::add()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    ///
+    /// let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    ///
+    /// let poller = Poller::new()?;
+    /// poller.add_nonblocking(&source, Event::readable(7))?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    #[cfg(unix)]
+    pub fn add_nonblocking(&self, source: impl Source, interest: Event) -> io::Result<()> {
+        let raw = source.raw();
+        let flags = syscall!(fcntl(raw, libc::F_GETFL))?;
+        if flags & libc::O_NONBLOCK == 0 {
+            syscall!(fcntl(raw, libc::F_SETFL, flags | libc::O_NONBLOCK))?;
+        }
+        self.add(raw, interest)
+    }
+
+    /// Adds a file descriptor or socket to the poller until the returned guard is dropped.
+    ///
+    /// This method has the same behavior as [`add()`][`Poller::add()`], except that the file
+    /// descriptor or socket is [`delete()`][`Poller::delete()`]d when the returned
+    /// [`Registration`] is dropped. Dropping the guard right before closing the file descriptor or
+    /// socket guarantees the poller never keeps a stale registration around if its number is
+    /// reused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    ///
+    /// let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// source.set_nonblocking(true)?;
+    ///
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// let registration = poller.register(&source, Event::readable(7))?;
+    /// registration.modify(Event::none(7))?;
+    ///
+    /// drop(registration);
+    /// assert!(!poller.contains(&source));
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn register(&self, source: impl Source, interest: Event) -> io::Result<Registration<'_>> {
+        let raw = source.raw();
+        self.add(raw, interest)?;
+        Ok(Registration { poller: self, raw })
+    }
+
+    /// Creates an empty group of file descriptors and sockets that are deleted together.
+    ///
+    /// Sources added through [`Group::add()`] are [`delete()`][`Poller::delete()`]d from the
+    /// poller when the returned [`Group`] is dropped. This is like [`register()`], but for
+    /// resources that own several file descriptors or sockets, for example a connection with a
+    /// socket and a timer, so that none of them is left behind when the resource goes away.
+    ///
+    /// [`register()`]: `Poller::register()`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    /// use std::net::TcpListener;
+    ///
+    /// let a = TcpListener::bind("127.0.0.1:0")?;
+    /// let b = TcpListener::bind("127.0.0.1:0")?;
+    /// a.set_nonblocking(true)?;
+    /// b.set_nonblocking(true)?;
+    ///
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// let mut group = poller.registration_group();
+    /// group.add(&a, Event::readable(1))?;
+    /// group.add(&b, Event::readable(2))?;
+    ///
+    /// drop(group);
+    /// assert!(!poller.contains(&a));
+    /// assert!(!poller.contains(&b));
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn registration_group(&self) -> Group<'_> {
+        Group {
+            poller: self,
+            sources: Vec::new(),
+        }
+    }
+
+    /// Starts a [`Transaction`] of additions, modifications, and deletions.
+    ///
+    /// The changes are queued without touching the poller, and are applied together by
+    /// [`commit()`][`Transaction::commit()`], which keeps [`wait()`][`Poller::wait()`] from
+    /// fetching events in the middle of them. Dropping the transaction without committing
+    /// discards the changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    /// use std::net::TcpListener;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0")?;
+    /// let other = TcpListener::bind("127.0.0.1:0")?;
+    ///
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// poller.add(&listener, Event::readable(1))?;
+    ///
+    /// // Switch from accepting on one listener to the other.
+    /// let mut txn = poller.begin();
+    /// txn.delete(&listener);
+    /// txn.add(&other, Event::readable(2));
+    /// txn.commit()?;
+    ///
+    /// assert!(!poller.contains(&listener));
+    /// assert!(poller.contains(&other));
+    /// # poller.delete(&other)?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn begin(&self) -> Transaction<'_> {
+        Transaction {
+            poller: self,
+            ops: Vec::new(),
+        }
+    }
+
+    /// Adds a file descriptor or socket with a callback that handles its events.
+    ///
+    /// The handler is called by [`dispatch()`][`Poller::dispatch()`] for every event with the
+    /// source's key, and dropped when the source is [`delete()`][`Poller::delete()`]d. Interest
+    /// still needs to be re-enabled with [`modify()`][`Poller::modify()`] after an event, which
+    /// the handler can do itself.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the poller doesn't track registrations, see
+    /// [`Builder::track_registrations()`], or in the same situations as [`add()`][`Poller::add()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    ///
+    /// let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// source.set_nonblocking(true)?;
+    ///
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// poller.add_with_handler(&source, Event::readable(7), |ev| {
+    ///     println!("listener is ready: {:?}", ev);
+    /// })?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn add_with_handler(
+        &self,
+        source: impl Source,
+        interest: Event,
+        handler: impl FnMut(Event) + Send + 'static,
+    ) -> io::Result<()> {
+        let raw = source.raw();
+        self.require_tracking("adding a source with a handler")?;
+        self.add_registration(raw, interest, None)?;
+        self.handlers.lock().unwrap().insert(raw, Box::new(handler));
+        Ok(())
+    }
+
+    /// Waits for events and calls the handler of each source that has one.
+    ///
+    /// This is [`wait()`][`Poller::wait()`] followed by calling the handler added with
+    /// [`add_with_handler()`][`Poller::add_with_handler()`] for each event, looked up by key.
+    /// Events without a handler, such as expired timers, are appended to `unhandled` instead.
+    /// Returns the number of handlers called.
+    ///
+    /// No lock is held while a handler runs, so handlers can call back into the poller without
+    /// deadlocking: to re-enable interest, add and delete sources, or even delete their own
+    /// source. A handler that waits, directly or through a nested
+    /// [`dispatch()`][`Poller::dispatch()`], delays the handlers of the remaining events.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    /// use std::time::Duration;
+    ///
+    /// let poller = Poller::new()?;
+    ///
+    /// let mut unhandled = Vec::new();
+    /// let n = poller.dispatch(&mut unhandled, Some(Duration::from_secs(0)))?;
+    /// assert_eq!(n, 0);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn dispatch(
+        &self,
+        unhandled: &mut Vec<Event>,
+        timeout: Option<Duration>,
+    ) -> io::Result<usize> {
+        let mut events = Vec::new();
+        self.wait(&mut events, timeout)?;
+        Ok(self.run_handlers(events, unhandled))
+    }
+
+    /// Calls the handler of each event's source, appending events without one to `unhandled`.
+    fn run_handlers(&self, events: Vec<Event>, unhandled: &mut Vec<Event>) -> usize {
+        // Sources with a handler are always tracked.
+        if !self.tracking() {
+            unhandled.extend(events);
+            return 0;
+        }
+        let mut called = 0;
+        for ev in events {
+            let raw = self
+                .registrations
+                .lock()
+                .unwrap()
+                .keys
+                .get(&ev.key)
+                .cloned();
+            let handler = raw.and_then(|raw| {
+                let handler = self.handlers.lock().unwrap().remove(&raw)?;
+                Some((raw, handler))
+            });
+
+            match handler {
+                Some((raw, mut handler)) => {
+                    handler(ev);
+                    called += 1;
+
+                    // Put it back unless the source was deleted or given a new handler.
+                    if self.contains(raw) {
+                        self.handlers.lock().unwrap().entry(raw).or_insert(handler);
+                    }
+                }
+                None => unhandled.push(ev),
+            }
+        }
+        called
+    }
+
+    /// Adds a file descriptor or socket whose events are delivered at most once per `cooldown`.
+    ///
+    /// This protects the event loop from a peer that makes a socket ready at an extreme rate.
+    /// Since polling is done in oneshot mode, a file descriptor or socket is reported once and
+    /// then waits to be re-enabled with [`modify()`][`Poller::modify()`]. For a rate-limited one,
+    /// re-enabling interest less than `cooldown` after its last event was delivered is deferred:
+    /// [`interest()`][`Poller::interest()`] keeps reporting no interest until the cooldown is
+    /// over, at which point [`wait()`][`Poller::wait()`] re-enables it, waking up if needed.
+    ///
+    /// This adds latency of up to `cooldown` to every event after the first in a burst. Disabling
+    /// interest and deleting are never deferred.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the poller doesn't track registrations, see
+    /// [`Builder::track_registrations()`], or in the same situations as [`add()`][`Poller::add()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    /// use std::time::Duration;
+    ///
+    /// let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// source.set_nonblocking(true)?;
+    ///
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// poller.add_with_rate_limit(&source, Event::readable(7), Duration::from_millis(1))?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn add_with_rate_limit(
+        &self,
+        source: impl Source,
+        interest: Event,
+        cooldown: Duration,
+    ) -> io::Result<()> {
+        let raw = source.raw();
+        self.require_tracking("adding a source with a rate limit")?;
+        self.add_registration(raw, interest, None)?;
+        if let Some(registration) = self.registrations.lock().unwrap().sources.get_mut(&raw) {
+            registration.cooldown = Some(cooldown);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if sources are recorded in the bookkeeping.
+    fn tracking(&self) -> bool {
+        self.tracking
+    }
+
+    /// Fails unless the poller tracks registrations, which `what` requires.
+    fn require_tracking(&self, what: &str) -> io::Result<()> {
+        if self.tracking {
+            return Ok(());
+        }
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("{} requires the poller to track registrations", what),
+        ))
+    }
+
+    /// Fails if `key` is reserved for internal use or for notifications.
+    fn check_key(&self, key: usize) -> io::Result<()> {
+        if key == NOTIFY_KEY {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the key is not allowed to be `usize::MAX`",
+            ));
+        }
+        if self.notify_key == Some(key) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the key is reserved for notifications",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Adds a file descriptor or socket and records it in the bookkeeping, if tracking.
+    fn add_registration(
+        &self,
+        raw: RawSource,
+        interest: Event,
+        label: Option<&'static str>,
+    ) -> io::Result<()> {
+        self.check_key(interest.key)?;
+        if self.draining.load(Ordering::SeqCst) {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "the poller is draining and does not accept new file descriptors or sockets",
+            ));
+        }
+        self.poller.add(raw, interest)?;
+        if let Some(label) = label {
+            log::trace!("Poller::add({:?}, {:?}): {}", raw, interest, label);
+        }
+        if !self.tracking() {
+            return Ok(());
+        }
+
+        let mut registrations = self.registrations.lock().unwrap();
+        registrations.insert(
+            raw,
+            Entry {
+                registered: interest,
+                interest,
+                label,
+                tier: 0,
+                cooldown: None,
+                last_reported: None,
+                wants_write: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// Modifies the interest in a file descriptor or socket.
+    ///
+    /// This method has the same behavior as [`add()`][`Poller::add()`] except it modifies the
+    /// interest of a previously added file descriptor or socket.
+    ///
+    /// To use this method with a file descriptor or socket, you must first add it using
+    /// [`add()`][`Poller::add()`].
+    ///
+    /// Note that interest in I/O events needs to be re-enabled using
+    /// [`modify()`][`Poller::modify()`] again after an event is delivered if we're interested in
+    /// the next event of the same kind.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error in the following situations:
+    ///
+    /// * If `key` equals `usize::MAX` because that key is reserved for internal use.
+    /// * If `key` is the one notifications are reported with, see
+    ///   [`Builder::notify_as_event()`].
+    /// * If an error is returned by the syscall.
+    ///
+    /// # Examples
+    ///
+    /// To enable interest in all events:
+    ///
+    /// ```no_run
+    /// # use polling::{Event, Poller};
+    /// # let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// # let key = 7;
+    /// # let poller = Poller::new()?;
+    /// # poller.add(&source, Event::none(key))?;
+    /// poller.modify(&source, Event::all(key))?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    ///
+    /// To enable interest in readable events and disable interest in writable events:
+    ///
+    /// ```no_run
+    /// # use polling::{Event, Poller};
+    /// # let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// # let key = 7;
+    /// # let poller = Poller::new()?;
+    /// # poller.add(&source, Event::none(key))?;
+    /// poller.modify(&source, Event::readable(key))?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    ///
+    /// To disable interest in readable events and enable interest in writable events:
+    ///
+    /// ```no_run
+    /// # use polling::{Event, Poller};
+    /// # let poller = Poller::new()?;
+    /// # let key = 7;
+    /// # let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// # poller.add(&source, Event::none(key))?;
+    /// poller.modify(&source, Event::writable(key))?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    ///
+    /// To disable interest in all events:
+    ///
+    /// ```no_run
+    /// # use polling::{Event, Poller};
+    /// # let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// # let key = 7;
+    /// # let poller = Poller::new()?;
+    /// # poller.add(&source, Event::none(key))?;
+    /// poller.modify(&source, Event::none(key))?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn modify(&self, source: impl Source, interest: Event) -> io::Result<()> {
+        self.check_key(interest.key)?;
+        let raw = source.raw();
+        if !self.tracking() {
+            return self.poller.modify(raw, interest);
+        }
+
+        let mut interest = interest;
+        if let Some(wants_write) = self
+            .registrations
+            .lock()
+            .unwrap()
+            .get(raw)
+            .and_then(|registration| registration.wants_write)
+        {
+            interest.writable = wants_write;
+        }
+        if self.defer_rearm(raw, interest)? {
+            return Ok(());
+        }
+        self.poller.modify(raw, interest)?;
+
+        let mut registrations = self.registrations.lock().unwrap();
+        if let Some(label) = registrations
+            .update(raw, interest)
+            .and_then(|registration| registration.label)
+        {
+            log::trace!("Poller::modify({:?}, {:?}): {}", raw, interest, label);
+        }
+        Ok(())
+    }
+
+    /// Re-enables interest in several file descriptors or sockets at once.
+    ///
+    /// Each source is [`modify()`][`Poller::modify()`]d with the interest it was last added or
+    /// modified with, so an event loop doesn't have to remember it after processing a batch of
+    /// events. Every source is re-armed even if re-arming another one fails, and the first
+    /// error is returned.
+    ///
+    /// The operating system is still called once per source, so this saves bookkeeping in the
+    /// caller rather than system calls.
+    ///
+    /// # Errors
+    ///
+    /// A source that isn't in the poller fails with [`io::ErrorKind::NotFound`], and so does
+    /// every source if the poller doesn't track registrations, see
+    /// [`Builder::track_registrations()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    ///
+    /// let a = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// let b = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// a.set_nonblocking(true)?;
+    /// b.set_nonblocking(true)?;
+    ///
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// poller.add(&a, Event::readable(1))?;
+    /// poller.add(&b, Event::readable(2))?;
+    ///
+    /// // ... wait for and handle events ...
+    ///
+    /// poller.rearm_many(&[&a, &b])?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn rearm_many<S: Source>(&self, sources: &[S]) -> io::Result<()> {
+        let mut res = Ok(());
+        for source in sources {
+            let raw = source.raw();
+            let registered = self
+                .registrations
+                .lock()
+                .unwrap()
+                .get(raw)
+                .map(|registration| registration.registered);
+            let rearmed = match registered {
+                Some(interest) => self.modify(raw, interest),
+                None => Err(io::ErrorKind::NotFound.into()),
+            };
+            if let Err(e) = rearmed {
+                if res.is_ok() {
+                    res = Err(e);
+                }
+            }
+        }
+        res
+    }
+
+    /// Removes a file descriptor or socket from the poller.
+    ///
+    /// Unlike [`add()`][`Poller::add()`], this method only removes the file descriptor or
+    /// socket from the poller without putting it back into blocking mode.
+    ///
+    /// Make sure to call this method before the file descriptor or socket is closed. The
+    /// operating system may reuse its number for the next one that is opened, and the poller
+    /// can't tell the two apart: [`modify()`][`Poller::modify()`] would then silently apply to
+    /// the new one, and [`contains()`][`Poller::contains()`] and
+    /// [`interest()`][`Poller::interest()`] would report stale information about it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    /// use std::net::TcpListener;
+    ///
+    /// let socket = TcpListener::bind("127.0.0.1:0")?;
+    /// socket.set_nonblocking(true)?;
+    /// let key = 7;
+    ///
+    /// let poller = Poller::new()?;
+    /// poller.add(&socket, Event::all(key))?;
+    /// poller.delete(&socket)?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn delete(&self, source: impl Source) -> io::Result<()> {
+        let raw = source.raw();
+        if !self.tracking() {
+            return self.poller.delete(raw);
+        }
+
+        let removed = self.registrations.lock().unwrap().remove(raw);
+        if let Some(registration) = removed {
+            if let Some(label) = registration.label {
+                log::trace!("Poller::delete({:?}): {}", raw, label);
+            }
+            if registration.cooldown.is_some() {
+                self.rearms.lock().unwrap().retain(|&(_, r, _)| r != raw);
+            }
+            self.handlers.lock().unwrap().remove(&raw);
+        }
+
+        self.poller.delete(raw)
+    }
+
+    /// Waits for at least one I/O event and returns the number of new events.
+    ///
+    /// New events will be appended to `events`. If necessary, make sure to clear the [`Vec`]
+    /// before calling [`wait()`][`Poller::wait()`]!
+    ///
+    /// This method will return with no new events if a notification is delivered by the
+    /// [`notify()`] method, or the timeout is reached. Sometimes it may even return with no events
+    /// spuriously.
+    ///
+    /// Only one thread can wait on I/O. If another thread is already in [`wait()`], concurrent
+    /// calls to this method will return immediately with no new events.
+    ///
+    /// If the operating system is ready to deliver a large number of events at once, this method
+    /// may decide to deliver them in smaller batches.
+    ///
+    /// I/O events are appended in the order the operating system reports them, which is not
+    /// specified and may differ between platforms. If a stable order is needed, for example to
+    /// make assertions deterministic or to dispatch round-robin by key, sort the new events after
+    /// this method returns. This costs a sort per call, but no additional allocation.
+    ///
+    /// Events that don't come from the operating system are always appended after the I/O
+    /// events, in a fixed order: first the notification event, if notifications are reported
+    /// with [`Builder::notify_as_event()`], then the expired timers. Timers are ordered by
+    /// deadline, and timers with the same deadline by key. So if a notification and a timer are
+    /// both pending when the poller wakes up, the notification is always reported first.
+    ///
+    /// [`notify()`]: `Poller::notify()`
+    /// [`wait()`]: `Poller::wait()`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    /// use std::net::TcpListener;
+    /// use std::time::Duration;
+    ///
+    /// let socket = TcpListener::bind("127.0.0.1:0")?;
+    /// socket.set_nonblocking(true)?;
+    /// let key = 7;
+    ///
+    /// let poller = Poller::new()?;
+    /// poller.add(&socket, Event::all(key))?;
+    ///
+    /// let mut events = Vec::new();
+    /// let n = poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    ///
+    /// Sort the new events by key:
+    ///
+    /// ```
+    /// # use polling::Poller;
+    /// # use std::time::Duration;
+    /// # let poller = Poller::new()?;
+    /// let mut events = Vec::new();
+    /// let n = poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    ///
+    /// let start = events.len() - n;
+    /// events[start..].sort_by_key(|ev| ev.key);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn wait(&self, events: &mut Vec<Event>, timeout: Option<Duration>) -> io::Result<usize> {
+        log::trace!("Poller::wait(_, {:?})", timeout);
+        self.wait_with_prepoll(events, timeout, || false)
+    }
+
+    /// Waits for at least one I/O event, running a hook right before blocking.
+    ///
+    /// This method behaves like [`wait()`][`Poller::wait()`], except that `pre` is invoked once
+    /// this thread has become the one waiting on I/O, just before the poller calls into the
+    /// operating system. If `pre` returns `true`, the caller has pending work of its own and the
+    /// poller only collects events that are already ready instead of blocking, as if the timeout
+    /// was zero.
+    ///
+    /// If another thread is already in [`wait()`][`Poller::wait()`], this method returns
+    /// immediately with no new events and `pre` is not invoked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::Poller;
+    /// use std::collections::VecDeque;
+    ///
+    /// let poller = Poller::new()?;
+    /// let mut tasks = VecDeque::new();
+    /// tasks.push_back("task");
+    ///
+    /// // Doesn't block because `tasks` is not empty.
+    /// let mut events = Vec::new();
+    /// poller.wait_with_prepoll(&mut events, None, || !tasks.is_empty())?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn wait_with_prepoll(
+        &self,
+        events: &mut Vec<Event>,
+        mut timeout: Option<Duration>,
+        pre: impl FnOnce() -> bool,
+    ) -> io::Result<usize> {
+        if let Some(mut lock) = self.lock_events() {
+            // Don't block if the caller has work to do.
+            if pre() {
+                log::trace!("wait: pre-poll hook requested a non-blocking wait");
+                timeout = Some(Duration::from_secs(0));
+            }
+
+            self.wait_locked(&mut lock, events, timeout)
+        } else {
+            log::trace!("wait: skipping because another thread is already waiting on I/O");
+            Ok(0)
+        }
+    }
+
+    /// Waits for I/O events, leaving their conversion into [`Event`]s for later.
+    ///
+    /// This is like [`wait()`][`Poller::wait()`], but the events are returned in the native
+    /// format of the operating system, in a [`RawBatch`] that can be sent to another thread and
+    /// converted there with [`RawBatch::events()`]. The polling thread can then go back to
+    /// waiting right away. Expired timers and [`interest()`][`Poller::interest()`] are handled
+    /// the same way as in [`wait()`][`Poller::wait()`].
+    ///
+    /// Each batch has its own native event list, which is allocated by this method.
+    ///
+    /// If another thread is already waiting on I/O, this method returns an empty batch right away.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    /// use std::net::TcpListener;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let socket = TcpListener::bind("127.0.0.1:0")?;
+    /// socket.set_nonblocking(true)?;
+    /// let key = 7;
+    ///
+    /// let poller = Poller::new()?;
+    /// poller.add(&socket, Event::readable(key))?;
+    ///
+    /// let batch = poller.poll_raw(Some(Duration::from_secs(0)))?;
+    /// let events = thread::spawn(move || batch.events().collect::<Vec<_>>())
+    ///     .join()
+    ///     .unwrap();
+    /// assert!(events.is_empty());
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn poll_raw(&self, timeout: Option<Duration>) -> io::Result<RawBatch> {
+        let mut batch = RawBatch {
+            events: sys::Events::new(),
+            extra: Vec::new(),
+        };
+        if let Some(_lock) = self.lock_events() {
+            self.poll_locked(&mut batch.events, timeout)?;
+            self.collect_extra(&mut batch.extra);
+        } else {
+            log::trace!("poll_raw: skipping because another thread is already waiting on I/O");
+        }
+        Ok(batch)
+    }
+
+    /// Waits for I/O events until an event with a particular key is delivered.
+    ///
+    /// This method calls [`wait()`][`Poller::wait()`] repeatedly, accumulating events into
+    /// `events`, until one of the new events has the given `key` or the timeout is reached. It
+    /// also returns early when [`wait()`][`Poller::wait()`] returns with no new events, which
+    /// happens when a notification is delivered by [`notify()`][`Poller::notify()`].
+    ///
+    /// Note that this is a user-space approximation: the operating system has no notion of
+    /// priority among file descriptors, so events for other keys are still delivered as they
+    /// become ready, and each of them costs another round trip into the kernel before the
+    /// awaited key is seen.
+    ///
+    /// Returns the total number of new events, which may not include `key` if this method
+    /// returned early.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    /// use std::net::TcpListener;
+    /// use std::time::Duration;
+    ///
+    /// let socket = TcpListener::bind("127.0.0.1:0")?;
+    /// socket.set_nonblocking(true)?;
+    ///
+    /// let poller = Poller::new()?;
+    /// poller.add(&socket, Event::readable(7))?;
+    ///
+    /// let mut events = Vec::new();
+    /// poller.wait_until_key(&mut events, 7, Some(Duration::from_millis(10)))?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn wait_until_key(
+        &self,
+        events: &mut Vec<Event>,
+        key: usize,
+        timeout: Option<Duration>,
+    ) -> io::Result<usize> {
+        log::trace!("Poller::wait_until_key(_, {}, {:?})", key, timeout);
+        // A timeout too large to represent as an instant is the same as no timeout.
+        let deadline = timeout.and_then(|t| Instant::now().checked_add(t));
+        let mut n = 0;
+
+        loop {
+            let timeout = deadline.map(|d| {
+                let now = Instant::now();
+                if d > now {
+                    d - now
+                } else {
+                    Duration::from_secs(0)
+                }
+            });
+
+            let len = events.len();
+            let new = self.wait(events, timeout)?;
+            n += new;
+
+            let found = events[len..].iter().any(|ev| ev.key == key);
+            let expired = timeout == Some(Duration::from_secs(0));
+            if found || expired || new == 0 {
+                return Ok(n);
+            }
+        }
+    }
+
+    /// Waits for I/O events and keeps collecting them until all ready events have been reported.
+    ///
+    /// This method behaves like [`wait()`][`Poller::wait()`], except that when the operating
+    /// system fills the poller's internal event buffer, which means more events are likely ready,
+    /// it immediately polls again with a zero timeout to collect the remainder within the same
+    /// call. Because interest is disabled for every delivered event, each follow-up poll only
+    /// reports events that haven't been reported yet.
+    ///
+    /// This trades latency for completeness: with enough ready file descriptors or sockets, a
+    /// single call could do an unbounded amount of work, so the number of follow-up polls is
+    /// capped at 16. If the cap is reached, the remaining events are delivered by the next call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    /// use std::net::TcpListener;
+    /// use std::time::Duration;
+    ///
+    /// let socket = TcpListener::bind("127.0.0.1:0")?;
+    /// socket.set_nonblocking(true)?;
+    ///
+    /// let poller = Poller::new()?;
+    /// poller.add(&socket, Event::all(7))?;
+    ///
+    /// let mut events = Vec::new();
+    /// let n = poller.wait_exhaustive(&mut events, Some(Duration::from_secs(1)))?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn wait_exhaustive(
+        &self,
+        events: &mut Vec<Event>,
+        timeout: Option<Duration>,
+    ) -> io::Result<usize> {
+        log::trace!("Poller::wait_exhaustive(_, {:?})", timeout);
+
+        if let Some(mut lock) = self.lock_events() {
+            let mut n = self.wait_locked(&mut lock, events, timeout)?;
+
+            // Drain the remaining ready events.
+            let mut rounds = 0;
+            while lock.is_full() && rounds < EXHAUSTIVE_ROUNDS {
+                log::trace!("wait_exhaustive: event buffer is full, polling again");
+                n += self.wait_locked(&mut lock, events, Some(Duration::from_secs(0)))?;
+                rounds += 1;
+            }
+
+            Ok(n)
+        } else {
+            log::trace!("wait: skipping because another thread is already waiting on I/O");
+            Ok(0)
+        }
+    }
+
+    /// Locks the event buffer to wait on I/O, unless another thread is waiting or committing.
+    fn lock_events(&self) -> Option<MutexGuard<'_, sys::Events>> {
+        if self.commits.load(Ordering::SeqCst) > 0 {
+            log::trace!("wait: skipping because a transaction is being committed");
+            return None;
+        }
+        self.events.try_lock().ok()
+    }
+
+    /// Waits for I/O events while holding the lock on the event buffer.
+    fn wait_locked(
+        &self,
+        lock: &mut sys::Events,
+        events: &mut Vec<Event>,
+        timeout: Option<Duration>,
+    ) -> io::Result<usize> {
+        self.poll_locked(lock, timeout)?;
+
+        // Collect events.
+        let len = events.len();
+        events.extend(lock.iter().filter(|ev| ev.key != NOTIFY_KEY));
+        self.collect_extra(events);
+
+        Ok(events.len() - len)
+    }
+
+    /// Waits for I/O events into the native list and updates the bookkeeping.
+    fn poll_locked(&self, lock: &mut sys::Events, mut timeout: Option<Duration>) -> io::Result<()> {
+        // Wake up in time for the nearest timer and deferred re-arm.
+        if let Some(until) = self.time_remaining() {
+            timeout = Some(timeout.map_or(until, |t| t.min(until)));
+        }
+        if let Some(due) = self.apply_rearms() {
+            let now = self.now();
+            let until = if due > now {
+                due - now
+            } else {
+                Duration::from_secs(0)
+            };
+            timeout = Some(timeout.map_or(until, |t| t.min(until)));
+        }
+
+        // Let the software timer wake us up at the precise deadline, if it can be represented.
+        let deadline = timeout
+            .filter(|&t| t > Duration::from_secs(0))
+            .and_then(|t| Instant::now().checked_add(t));
+        let software_timer = match (&self.software_timer, deadline) {
+            (Some(timer), Some(deadline)) => {
+                timer.set(Some(deadline));
+                Some(timer)
+            }
+            _ => None,
+        };
+
+        // Wait for I/O events.
+        #[cfg(feature = "metrics")]
+        let start = Instant::now();
+        let res = self.poller.wait(lock, timeout);
+        #[cfg(feature = "metrics")]
+        {
+            // Don't hold the lock while the hook runs, so it can replace itself.
+            let hook = self.wait_hook.lock().unwrap().clone();
+            if let Some(hook) = hook {
+                hook(start.elapsed());
+            }
+        }
+        if let Some(timer) = software_timer {
+            timer.set(None);
+        }
+        res?;
+        *self.last_wake.lock().unwrap() = Some(Instant::now());
+        self.last_full.store(lock.is_full(), Ordering::SeqCst);
+
+        // Clear the notification, if any.
+        self.notified.swap(false, Ordering::SeqCst);
+
+        if !self.tracking() {
+            return Ok(());
+        }
+
+        // Interest in delivered events is now disabled.
+        let now = self.now();
+        let mut registrations = self.registrations.lock().unwrap();
+        for ev in lock.iter().filter(|ev| ev.key != NOTIFY_KEY) {
+            if let Some(registration) = registrations.get_by_key_mut(ev.key) {
+                registration.interest = sys::disarm(registration.interest, ev);
+                if registration.cooldown.is_some() {
+                    registration.last_reported = Some(now);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Queues re-arming a rate-limited source until its cooldown is over.
+    ///
+    /// Returns `false` if the source should be modified right away.
+    fn defer_rearm(&self, raw: RawSource, interest: Event) -> io::Result<bool> {
+        let due = match self.registrations.lock().unwrap().get(raw) {
+            Some(&Entry {
+                cooldown: Some(cooldown),
+                last_reported,
+                ..
+            }) => last_reported.and_then(|last| last.checked_add(cooldown)),
+            _ => return Ok(false),
+        };
+
+        let mut rearms = self.rearms.lock().unwrap();
+        rearms.retain(|&(_, r, _)| r != raw);
+        let due = match due {
+            Some(due) if due > self.now() && (interest.readable || interest.writable) => due,
+            _ => return Ok(false),
+        };
+        rearms.push((due, raw, interest));
+        if let Some(registration) = self.registrations.lock().unwrap().sources.get_mut(&raw) {
+            registration.registered = interest;
+        }
+        drop(rearms);
+        log::trace!("Poller::modify({:?}, {:?}): deferred", raw, interest);
+
+        // Let the current wait take the new deadline into account.
+        self.wake()?;
+        Ok(true)
+    }
+
+    /// Re-arms rate-limited sources whose cooldown is over.
+    ///
+    /// Returns when the next deferred re-arm is due. A source that fails to be re-armed, for
+    /// example because it was closed without being deleted, is dropped from the queue without
+    /// failing the wait.
+    fn apply_rearms(&self) -> Option<Instant> {
+        // Only rate-limited sources are re-armed, and they are always tracked.
+        if !self.tracking() {
+            return None;
+        }
+        let mut rearms = self.rearms.lock().unwrap();
+        if rearms.is_empty() {
+            return None;
+        }
+
+        let now = self.now();
+        let mut next: Option<Instant> = None;
+        let mut i = 0;
+        while i < rearms.len() {
+            let (due, raw, interest) = rearms[i];
+            if due > now {
+                next = Some(next.map_or(due, |next| next.min(due)));
+                i += 1;
+                continue;
+            }
+            rearms.swap_remove(i);
+            match self.poller.modify(raw, interest) {
+                Ok(()) => {
+                    self.registrations.lock().unwrap().update(raw, interest);
+                }
+                Err(e) => log::trace!("apply_rearms: dropping {:?}: {}", raw, e),
+            }
+        }
+        next
+    }
+
+    /// Appends the events that don't come from the operating system to `events`.
+    fn collect_extra(&self, events: &mut Vec<Event>) {
+        if let Some(key) = self.notify_key {
+            if self.user_notified.swap(false, Ordering::SeqCst) {
+                events.push(Event::readable(key));
+            }
+        }
+        self.collect_timers(events);
+    }
+
+    /// Appends expired timers to `events`.
+    fn collect_timers(&self, events: &mut Vec<Event>) {
+        if self.armed_timers.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let now = self.now();
+        let mut timers = self.timers.lock().unwrap();
+        while let Some(&Reverse((deadline, key))) = timers.peek() {
+            if deadline > now {
+                break;
+            }
+            timers.pop();
+            events.push(Event::readable(key));
+        }
+        self.armed_timers.store(timers.len(), Ordering::SeqCst);
+    }
+
+    /// Returns why the poller fell back to millisecond-precision timeouts, if it did.
+    ///
+    /// On Linux and Android, the poller uses a timerfd for precise timeouts. If creating it fails,
+    /// for example because the kernel is too old (`ENOSYS`), the process is out of file
+    /// descriptors (`EMFILE`), or a sandbox forbids it, the poller keeps working but rounds
+    /// timeouts up to a whole millisecond, and this method returns the error that caused it.
+    ///
+    /// Returns `None` if the timerfd was created, if it was disabled with
+    /// [`Builder::without_timer()`], and always on other platforms.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::Poller;
+    ///
+    /// let poller = Poller::new()?;
+    /// if let Some(e) = poller.timer_fallback_reason() {
+    ///     eprintln!("timeouts are limited to millisecond precision: {}", e);
+    /// }
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn timer_fallback_reason(&self) -> Option<&io::Error> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        return self.poller.timer_error();
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        return None;
+    }
+
+    /// Returns the epoll file descriptor, to correlate `strace` output with a particular poller.
+    ///
+    /// The file descriptor is owned by the poller and must not be closed or used to add, modify,
+    /// or delete file descriptors behind the poller's back. It is the same number the poller's
+    /// [`Debug`][`fmt::Debug`] output shows.
+    ///
+    /// This method is only available on Linux and Android.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::Poller;
+    ///
+    /// let poller = Poller::new()?;
+    /// eprintln!("polling with epoll fd {}", poller.raw_epoll_fd());
+    /// # std::io::Result::Ok(())
+    /// ```
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn raw_epoll_fd(&self) -> std::os::unix::io::RawFd {
+        self.poller.epoll_fd()
+    }
+
+    /// Returns a [`LoopSource`] for driving this poller from a foreign main loop.
+    ///
+    /// This method is only available on Linux and Android.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::Poller;
+    ///
+    /// let poller = Poller::new()?;
+    /// let mut source = poller.loop_source();
+    ///
+    /// // With nothing added, there is no timeout to honor and nothing to dispatch.
+    /// assert_eq!(source.prepare(), None);
+    /// assert!(!source.check()?);
+    /// # std::io::Result::Ok(())
+    /// ```
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn loop_source(&self) -> LoopSource<'_> {
+        LoopSource {
+            poller: self,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Returns the instant the last [`wait()`][`Poller::wait()`] returned from the operating
+    /// system.
+    ///
+    /// The instant is captured right after the operating system reports events, before they are
+    /// collected into the caller's list, so it isn't skewed by the time taken to dispatch them.
+    /// This makes it suitable for measuring the latency from an event becoming ready to it being
+    /// handled.
+    ///
+    /// Returns `None` if no wait has completed yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::Poller;
+    /// use std::time::Duration;
+    ///
+    /// let poller = Poller::new()?;
+    /// assert!(poller.last_wake_time().is_none());
+    ///
+    /// let mut events = Vec::new();
+    /// poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    /// assert!(poller.last_wake_time().is_some());
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn last_wake_time(&self) -> Option<Instant> {
+        *self.last_wake.lock().unwrap()
+    }
+
+    /// Sets a callback that receives how long each wait blocked in the operating system.
+    ///
+    /// The callback is called right after the poller returns from the operating system, with
+    /// the time spent there, for every [`wait()`][`Poller::wait()`] and its variants, including
+    /// waits with a zero timeout and waits that fail. It can feed a latency histogram, for
+    /// example. It runs on the waiting thread and delays the delivery of events, so it should be
+    /// quick. Setting a callback replaces the previous one.
+    ///
+    /// The callback runs while the poller is held for waiting, so it must not commit a
+    /// [`Transaction`], as the commit would never finish. It can replace itself by setting
+    /// another callback.
+    ///
+    /// This method is only available with the `metrics` feature. Without it, waits aren't timed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::Poller;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let poller = Poller::new()?;
+    /// let waits = Arc::new(AtomicUsize::new(0));
+    /// let counter = waits.clone();
+    /// poller.set_wait_hook(move |blocked: Duration| {
+    ///     counter.fetch_add(1, Ordering::SeqCst);
+    /// });
+    ///
+    /// let mut events = Vec::new();
+    /// poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    /// assert_eq!(waits.load(Ordering::SeqCst), 1);
+    /// # std::io::Result::Ok(())
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn set_wait_hook(&self, hook: impl Fn(Duration) + Send + Sync + 'static) {
+        *self.wait_hook.lock().unwrap() = Some(Arc::new(hook));
+    }
+
+    /// Returns `true` if the last [`wait()`][`Poller::wait()`] received as many events from the
+    /// operating system as fit in the poller's internal buffer.
+    ///
+    /// A full buffer means more events were likely ready and will be delivered by the following
+    /// wait. An event loop can use this to poll again right away with a zero timeout, or switch
+    /// to [`wait_exhaustive()`][`Poller::wait_exhaustive()`]. After
+    /// [`wait_exhaustive()`][`Poller::wait_exhaustive()`], this reports on its last round.
+    ///
+    /// On platforms that use `poll()`, all ready file descriptors are always reported at once and
+    /// this method always returns `false`.
+    pub fn last_wait_was_full(&self) -> bool {
+        self.last_full.load(Ordering::SeqCst)
+    }
+
+    /// Arms a timer that expires at `deadline`.
+    ///
+    /// Once the deadline is reached, [`wait()`][`Poller::wait()`] reports the timer as
+    /// `Event { key, readable: true, writable: false }` after any I/O events, and the timer is
+    /// disarmed. Timers are kept in a min-heap, and [`wait()`][`Poller::wait()`] never blocks past
+    /// the nearest deadline, even if a longer timeout is passed to it.
+    ///
+    /// Any number of timers can be armed, including several under the same key. Timers can't be
+    /// cancelled, so a generation counter in the key (see [`Token`]) is the way to recognize and
+    /// ignore timers that are no longer relevant.
+    ///
+    /// If the new timer becomes the nearest one, the current or the following invocation of
+    /// [`wait()`][`Poller::wait()`] is woken up so that it can take the new deadline into account.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if `key` equals `usize::MAX` because that key is reserved for
+    /// internal use, or if it is the one notifications are reported with, see
+    /// [`Builder::notify_as_event()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let poller = Poller::new()?;
+    /// poller.arm_timer(7, Instant::now() + Duration::from_millis(10))?;
+    ///
+    /// let mut events = Vec::new();
+    /// while events.is_empty() {
+    ///     poller.wait(&mut events, None)?;
+    /// }
+    /// assert_eq!(events, [Event::readable(7)]);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn arm_timer(&self, key: usize, deadline: Instant) -> io::Result<()> {
+        log::trace!("Poller::arm_timer({}, {:?})", key, deadline);
+        self.check_key(key)?;
+
+        let nearest = {
+            let mut timers = self.timers.lock().unwrap();
+            timers.push(Reverse((deadline, key)));
+            self.armed_timers.store(timers.len(), Ordering::SeqCst);
+            timers.peek() == Some(&Reverse((deadline, key)))
+        };
+
+        // The thread in `wait()` might be blocking past the new deadline.
+        if nearest {
+            self.wake()?;
+        }
+        Ok(())
+    }
+
+    /// Returns how much time is left before the nearest armed timer expires.
+    ///
+    /// Returns `None` if no timer is armed, and a zero duration if a timer has already expired
+    /// but hasn't been reported by [`wait()`][`Poller::wait()`] yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::Poller;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let poller = Poller::new()?;
+    /// assert_eq!(poller.time_remaining(), None);
+    ///
+    /// poller.arm_timer(7, Instant::now() + Duration::from_secs(10))?;
+    /// assert!(poller.time_remaining().unwrap() <= Duration::from_secs(10));
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn time_remaining(&self) -> Option<Duration> {
+        self.next_timer().map(|deadline| {
+            let now = self.now();
+            if deadline > now {
+                deadline - now
+            } else {
+                Duration::from_secs(0)
+            }
+        })
+    }
+
+    /// Returns the current instant, as far as timers are concerned.
+    fn now(&self) -> Instant {
+        #[cfg(feature = "test-util")]
+        {
+            if let Some(clock) = &self.clock {
+                return clock.now();
+            }
+        }
+        Instant::now()
+    }
+
+    /// Returns the deadline of the nearest armed timer.
+    fn next_timer(&self) -> Option<Instant> {
+        if self.armed_timers.load(Ordering::SeqCst) == 0 {
+            return None;
+        }
+        let timers = self.timers.lock().unwrap();
+        timers.peek().map(|&Reverse((deadline, _))| deadline)
+    }
+
+    /// Returns when the nearest deferred re-arm is due.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn next_rearm(&self) -> Option<Instant> {
+        if !self.tracking() {
+            return None;
+        }
+        let rearms = self.rearms.lock().unwrap();
+        rearms.iter().map(|&(due, _, _)| due).min()
+    }
+
+    /// Keeps dispatching events until everything is deleted from the poller or `deadline` passes.
+    ///
+    /// This is an orderly shutdown sequence: from now on, [`add()`][`Poller::add()`] and the
+    /// other methods adding file descriptors or sockets fail, while existing ones keep getting
+    /// events. Each delivered event is passed to `handler`, which is expected to
+    /// [`modify()`][`Poller::modify()`] a file descriptor or socket that still has work to do and
+    /// [`delete()`][`Poller::delete()`] one that is done.
+    ///
+    /// Returns `true` once everything has been deleted, or `false` if the deadline passed first,
+    /// in which case the caller should forcibly close whatever is left, as reported by
+    /// [`dump_registrations()`][`Poller::dump_registrations()`]. Errors returned by `handler` are
+    /// propagated.
+    ///
+    /// The poller keeps rejecting new file descriptors and sockets after this method returns.
+    /// While another thread is in [`wait()`][`Poller::wait()`], that thread gets the events
+    /// instead, and this method only checks every millisecond whether everything was deleted.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the poller doesn't track registrations, see
+    /// [`Builder::track_registrations()`], because it couldn't tell when all sources are gone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::Poller;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let poller = Poller::builder().track_registrations().build()?;
+    ///
+    /// let deadline = Instant::now() + Duration::from_secs(5);
+    /// let drained = poller.drain(deadline, |ev| {
+    ///     // Finish in-flight work for `ev.key`, then delete it.
+    ///     Ok(())
+    /// })?;
+    /// assert!(drained);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn drain(
+        &self,
+        deadline: Instant,
+        mut handler: impl FnMut(Event) -> io::Result<()>,
+    ) -> io::Result<bool> {
+        log::trace!("Poller::drain({:?})", deadline);
+        self.require_tracking("draining")?;
+        self.draining.store(true, Ordering::SeqCst);
+
+        let mut events = Vec::new();
+        loop {
+            if self.registrations.lock().unwrap().sources.is_empty() {
+                return Ok(true);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(false);
+            }
+
+            events.clear();
+            match self.lock_events() {
+                Some(mut lock) => {
+                    self.wait_locked(&mut lock, &mut events, Some(deadline - now))?;
+                }
+                None => {
+                    // Another thread gets the events, give it time to delete the sources.
+                    thread::sleep((deadline - now).min(Duration::from_millis(1)));
+                }
+            }
+            for ev in &events {
+                handler(*ev)?;
+            }
+        }
+    }
+
+    /// Wakes up the current or the following invocation of [`wait()`].
+    ///
+    /// If no thread is calling [`wait()`] right now, this method will cause the following call
+    /// to wake up immediately.
+    ///
+    /// The woken up call returns without an event for the notification, unless the poller was
+    /// built with [`Builder::notify_as_event()`].
+    ///
+    /// [`wait()`]: `Poller::wait()`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::Poller;
+    ///
+    /// let poller = Poller::new()?;
     ///
     /// // Notify the poller.
     /// poller.notify()?;
@@ -430,6 +2610,14 @@ impl Poller {
     /// ```
     pub fn notify(&self) -> io::Result<()> {
         log::trace!("Poller::notify()");
+        if self.notify_key.is_some() {
+            self.user_notified.store(true, Ordering::SeqCst);
+        }
+        self.wake()
+    }
+
+    /// Wakes up the current or the following wait without reporting a notification event.
+    fn wake(&self) -> io::Result<()> {
         if self
             .notified
             .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
@@ -439,6 +2627,908 @@ impl Poller {
         }
         Ok(())
     }
+
+    /// Returns a handle that wakes up the poller from a signal handler.
+    ///
+    /// See [`WakerHandle`].
+    ///
+    /// This method is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn waker_handle(&self) -> WakerHandle {
+        WakerHandle {
+            poller: self.poller.clone(),
+        }
+    }
+
+    /// Wakes up all threads blocked on `condvar`, and then the current or the following
+    /// invocation of [`wait()`].
+    ///
+    /// This bridges sources that signal through a [`Condvar`] into the poll loop: the signalling
+    /// side updates the state guarded by the condvar's mutex and calls this method instead of
+    /// [`Condvar::notify_all()`], so both condvar waiters and the thread blocked in [`wait()`]
+    /// observe the change. The polling thread checks the state after [`wait()`] returns, or
+    /// before it blocks, with [`wait_with_prepoll()`].
+    ///
+    /// [`wait()`]: `Poller::wait()`
+    /// [`wait_with_prepoll()`]: `Poller::wait_with_prepoll()`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::Poller;
+    /// use std::sync::{Condvar, Mutex};
+    ///
+    /// let poller = Poller::new()?;
+    /// let ready = Mutex::new(false);
+    /// let condvar = Condvar::new();
+    ///
+    /// // Signal the condvar-based source.
+    /// *ready.lock().unwrap() = true;
+    /// poller.notify_on_condvar(&condvar)?;
+    ///
+    /// // The poll loop picks up the change.
+    /// let mut events = Vec::new();
+    /// poller.wait_with_prepoll(&mut events, None, || *ready.lock().unwrap())?;
+    /// assert!(*ready.lock().unwrap());
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn notify_on_condvar(&self, condvar: &Condvar) -> io::Result<()> {
+        condvar.notify_all();
+        self.notify()
+    }
+}
+
+/// Returns the number of bytes that can be read from a file descriptor or socket without blocking.
+///
+/// This is a hint obtained with `ioctl(FIONREAD)`, useful to size a buffer after a readable event
+/// is delivered. For a stream socket or pipe it is the number of queued bytes, while for a datagram
+/// socket it is typically the size of the next datagram, but the exact meaning depends on the
+/// platform and the kind of file descriptor.
+///
+/// This function is only available on Unix platforms.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use std::os::unix::net::UnixStream;
+///
+/// let (mut a, b) = UnixStream::pair()?;
+/// a.write_all(&[1, 2, 3])?;
+/// assert_eq!(polling::bytes_ready(&b)?, 3);
+/// # std::io::Result::Ok(())
+/// ```
+#[cfg(unix)]
+#[allow(clippy::incompatible_msrv)]
+pub fn bytes_ready(source: impl Source) -> io::Result<usize> {
+    let mut n: libc::c_int = 0;
+    syscall!(ioctl(
+        source.raw(),
+        libc::FIONREAD as _,
+        &mut n as *mut libc::c_int
+    ))?;
+    Ok(n as usize)
+}
+
+/// Takes the pending error of a socket, if there is one.
+///
+/// When a socket fails, for example because a connection was refused or reset, the poller only
+/// reports it as readable and writable, without an error code. This function reads and clears
+/// the error the kernel stored in the socket with `getsockopt(SO_ERROR)`. It returns `Ok(None)`
+/// if the event was genuine readiness.
+///
+/// Only sockets have a pending error. For pipes, terminals, and other file descriptors this
+/// function fails with `ENOTSOCK`, and the caller has to find the error by retrying the read or
+/// write.
+///
+/// This function is only available on Unix platforms.
+///
+/// # Examples
+///
+/// ```
+/// use std::os::unix::net::UnixStream;
+///
+/// let (a, _b) = UnixStream::pair()?;
+/// assert!(polling::socket_error(&a)?.is_none());
+/// # std::io::Result::Ok(())
+/// ```
+#[cfg(unix)]
+#[allow(clippy::incompatible_msrv)]
+pub fn socket_error(source: impl Source) -> io::Result<Option<io::Error>> {
+    let mut err: libc::c_int = 0;
+    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    syscall!(getsockopt(
+        source.raw(),
+        libc::SOL_SOCKET,
+        libc::SO_ERROR,
+        &mut err as *mut libc::c_int as *mut libc::c_void,
+        &mut len,
+    ))?;
+    if err == 0 {
+        Ok(None)
+    } else {
+        Ok(Some(io::Error::from_raw_os_error(err)))
+    }
+}
+
+/// Returns the maximum number of epoll watches a user may hold, across all of their pollers.
+///
+/// This reads the `fs.epoll.max_user_watches` sysctl from
+/// `/proc/sys/fs/epoll/max_user_watches`. Adding a source to a poller fails with `ENOSPC` once
+/// the limit is reached, so a service can compare it with [`Poller::epoll_watches()`] to warn
+/// before that happens. The limit is raised by writing the sysctl, which requires privileges.
+///
+/// This function is only available on Linux and Android.
+///
+/// # Errors
+///
+/// This function fails if the sysctl can't be read, for example because `/proc` isn't mounted,
+/// or if its contents aren't a number.
+///
+/// # Examples
+///
+/// ```
+/// use polling::Poller;
+///
+/// let poller = Poller::new()?;
+/// if let Ok(max) = polling::epoll_max_user_watches() {
+///     let headroom = max.saturating_sub(poller.epoll_watches());
+///     if headroom < max / 10 {
+///         eprintln!("approaching the epoll watch limit: {} left", headroom);
+///     }
+/// }
+/// # std::io::Result::Ok(())
+/// ```
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn epoll_max_user_watches() -> io::Result<usize> {
+    let contents = std::fs::read_to_string("/proc/sys/fs/epoll/max_user_watches")?;
+    contents.trim().parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid `max_user_watches`: {:?}", contents),
+        )
+    })
+}
+
+/// A snapshot of a file descriptor or socket in a [`Poller`].
+///
+/// Returned by [`Poller::dump_registrations()`]. Polling is always done in oneshot mode, so
+/// there are no other mode flags to report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegistrationInfo {
+    /// The file descriptor or socket.
+    pub source: RawSource,
+    /// The interest it was last added or modified with.
+    pub registered: Event,
+    /// The interest that is still armed, see [`Poller::interest()`].
+    pub armed: Event,
+    /// The label it was added with, see [`Poller::add_with_label()`].
+    pub label: Option<&'static str>,
+    /// The tier it is in, see [`Poller::set_tier()`].
+    pub tier: usize,
+}
+
+/// I/O events in the native format of the operating system.
+///
+/// Returned by [`Poller::poll_raw()`].
+pub struct RawBatch {
+    events: sys::Events,
+    /// The notification event and expired timers.
+    extra: Vec<Event>,
+}
+
+impl RawBatch {
+    /// Converts the events, in the same order as [`Poller::wait()`] would deliver them.
+    pub fn events(&self) -> impl Iterator<Item = Event> + '_ {
+        self.events
+            .iter()
+            .filter(|ev| ev.key != NOTIFY_KEY)
+            .chain(self.extra.iter().cloned())
+    }
+}
+
+impl fmt::Debug for RawBatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.events()).finish()
+    }
+}
+
+/// A file descriptor or socket added to a [`Poller`], which is deleted from it when dropped.
+///
+/// Created by [`Poller::register()`].
+#[derive(Debug)]
+pub struct Registration<'a> {
+    poller: &'a Poller,
+    raw: RawSource,
+}
+
+impl Registration<'_> {
+    /// Modifies the interest in the file descriptor or socket.
+    ///
+    /// See [`Poller::modify()`].
+    pub fn modify(&self, interest: Event) -> io::Result<()> {
+        self.poller.modify(self.raw, interest)
+    }
+
+    /// Deletes the file descriptor or socket from the poller, returning any error.
+    ///
+    /// Dropping the guard does the same, but ignores errors.
+    pub fn delete(self) -> io::Result<()> {
+        let res = self.poller.delete(self.raw);
+        std::mem::forget(self);
+        res
+    }
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        let _ = self.poller.delete(self.raw);
+    }
+}
+
+/// File descriptors and sockets added to a [`Poller`], which are deleted from it when dropped.
+///
+/// Created by [`Poller::registration_group()`].
+#[derive(Debug)]
+pub struct Group<'a> {
+    poller: &'a Poller,
+    sources: Vec<RawSource>,
+}
+
+impl Group<'_> {
+    /// Adds a file descriptor or socket to the poller and to the group.
+    ///
+    /// See [`Poller::add()`]. If adding fails, the source isn't added to the group either.
+    pub fn add(&mut self, source: impl Source, interest: Event) -> io::Result<()> {
+        let raw = source.raw();
+        self.poller.add(raw, interest)?;
+        self.sources.push(raw);
+        Ok(())
+    }
+
+    /// Returns the number of file descriptors and sockets in the group.
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Returns `true` if the group is empty.
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Deletes all file descriptors and sockets in the group from the poller.
+    ///
+    /// Every source is deleted even if deleting another one fails, and the first error is
+    /// returned. Dropping the group does the same, but ignores errors.
+    pub fn delete(mut self) -> io::Result<()> {
+        self.delete_all()
+    }
+
+    fn delete_all(&mut self) -> io::Result<()> {
+        let mut res = Ok(());
+        for raw in self.sources.drain(..) {
+            if let Err(e) = self.poller.delete(raw) {
+                if res.is_ok() {
+                    res = Err(e);
+                }
+            }
+        }
+        res
+    }
+}
+
+impl Drop for Group<'_> {
+    fn drop(&mut self) {
+        let _ = self.delete_all();
+    }
+}
+
+/// A [`Poller`] driven by a foreign main loop, such as GLib's or calloop's.
+///
+/// Created by [`Poller::loop_source()`]. It follows the shape of a `GSource`: in each iteration,
+/// the host loop
+///
+/// 1. calls [`prepare()`][`LoopSource::prepare()`] to learn how long it may block at most,
+/// 2. polls [`fd()`][`LoopSource::fd()`] for readability, with that timeout,
+/// 3. calls [`check()`][`LoopSource::check()`] once the poll returns, for any reason, and
+/// 4. calls [`dispatch()`][`LoopSource::dispatch()`] if `check()` returned `true`.
+///
+/// The file descriptor is the poller's epoll instance. It is readable while a source in the
+/// poller has an event ready or a [`Poller::notify()`] is pending, and stops being readable once
+/// `check()` has fetched the events. Timers and rate-limited re-arms don't make it readable when
+/// they are due, so the host loop must honor the timeout returned by `prepare()` for them to be
+/// reported on time. Arming a timer nearer than the others does make it readable, so that the host
+/// loop wakes up and calls `prepare()` again.
+///
+/// Don't call [`Poller::wait()`] from another thread while a host loop drives the poller: only
+/// one thread can wait, and events fetched by the other thread aren't seen by `check()`.
+///
+/// A mio reactor can be the host loop by registering `mio::unix::SourceFd(&source.fd())` with
+/// readable interest. Mio's registrations are edge-triggered, so after the fd is reported
+/// readable, keep calling `check()` and `dispatch()` until `check()` returns `false`, or events
+/// left over from a full batch won't wake the reactor up again.
+///
+/// This type is only available on Linux and Android.
+///
+/// # Examples
+///
+/// ```
+/// use polling::{Event, Poller};
+/// use std::net::{TcpListener, TcpStream};
+///
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let stream = TcpStream::connect(listener.local_addr()?)?;
+///
+/// let poller = Poller::new()?;
+/// poller.add(&stream, Event::writable(7))?;
+/// let mut source = poller.loop_source();
+///
+/// // The host loop polls `source.fd()` here, for at most `source.prepare()`.
+/// # std::thread::sleep(std::time::Duration::from_millis(50));
+/// if source.check()? {
+///     let mut events = Vec::new();
+///     source.dispatch(&mut events);
+///     assert_eq!(events, [Event::writable(7)]);
+/// }
+/// # std::io::Result::Ok(())
+/// ```
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug)]
+pub struct LoopSource<'a> {
+    poller: &'a Poller,
+    /// Events fetched by `check()` and not dispatched yet.
+    pending: Vec<Event>,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl LoopSource<'_> {
+    /// Returns the file descriptor the host loop polls for readability.
+    ///
+    /// This is the same file descriptor as [`Poller::raw_epoll_fd()`], and is owned by the
+    /// poller.
+    pub fn fd(&self) -> std::os::unix::io::RawFd {
+        self.poller.raw_epoll_fd()
+    }
+
+    /// Returns the longest the host loop may block before calling [`check()`].
+    ///
+    /// This is the time left before the nearest timer or rate-limited re-arm is due, zero if
+    /// events were checked but not dispatched yet, or `None` if only the file descriptor
+    /// becoming readable needs to wake the host loop up.
+    ///
+    /// [`check()`]: `LoopSource::check()`
+    pub fn prepare(&self) -> Option<Duration> {
+        if !self.pending.is_empty() {
+            return Some(Duration::from_secs(0));
+        }
+        let rearm = self.poller.next_rearm().map(|due| {
+            let now = self.poller.now();
+            if due > now {
+                due - now
+            } else {
+                Duration::from_secs(0)
+            }
+        });
+        match (self.poller.time_remaining(), rearm) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Fetches ready events without blocking, returning `true` if there are any to dispatch.
+    ///
+    /// This is a [`Poller::wait()`] with a zero timeout, so it also reports expired timers and
+    /// applies due re-arms. The events are kept for [`dispatch()`][`LoopSource::dispatch()`].
+    pub fn check(&mut self) -> io::Result<bool> {
+        if self.pending.is_empty() {
+            self.poller
+                .wait(&mut self.pending, Some(Duration::from_secs(0)))?;
+        }
+        Ok(!self.pending.is_empty())
+    }
+
+    /// Dispatches the events fetched by [`check()`][`LoopSource::check()`].
+    ///
+    /// Like [`Poller::dispatch()`], this calls the handler of each source that has one, appends
+    /// the other events to `unhandled`, and returns the number of handlers called.
+    pub fn dispatch(&mut self, unhandled: &mut Vec<Event>) -> usize {
+        let events = std::mem::replace(&mut self.pending, Vec::new());
+        self.poller.run_handlers(events, unhandled)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl AsRawFd for LoopSource<'_> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd()
+    }
+}
+
+/// Changes to the file descriptors and sockets in a [`Poller`], applied together.
+///
+/// Created by [`Poller::begin()`]. Queued changes aren't visible until
+/// [`commit()`][`Transaction::commit()`], and a dropped transaction discards them.
+///
+/// The operating system applies each change with its own syscall, so a commit isn't atomic to
+/// the kernel: a source can become ready as soon as it is added, and its event is reported by the
+/// next wait. A commit is atomic to [`Poller::wait()`], though. It wakes up a thread blocked in
+/// `wait()` and holds off waiting until all changes are applied, so events are never fetched
+/// with only some of the changes in place. While a commit is in progress, `wait()` returns
+/// immediately with no events, as it does when another thread is waiting.
+///
+/// Don't commit from the hook passed to [`Poller::wait_with_prepoll()`] or to
+/// `Poller::set_wait_hook()`, which run while the poller is held for waiting, as the commit would
+/// never finish.
+#[derive(Debug)]
+pub struct Transaction<'a> {
+    poller: &'a Poller,
+    ops: Vec<TransactionOp>,
+}
+
+/// A change queued in a [`Transaction`].
+#[derive(Debug, Clone, Copy)]
+enum TransactionOp {
+    Add(RawSource, Event),
+    Modify(RawSource, Event),
+    Delete(RawSource),
+}
+
+impl Transaction<'_> {
+    /// Queues adding a file descriptor or socket, see [`Poller::add()`].
+    pub fn add(&mut self, source: impl Source, interest: Event) {
+        self.ops.push(TransactionOp::Add(source.raw(), interest));
+    }
+
+    /// Queues modifying the interest in a file descriptor or socket, see [`Poller::modify()`].
+    pub fn modify(&mut self, source: impl Source, interest: Event) {
+        self.ops.push(TransactionOp::Modify(source.raw(), interest));
+    }
+
+    /// Queues deleting a file descriptor or socket, see [`Poller::delete()`].
+    pub fn delete(&mut self, source: impl Source) {
+        self.ops.push(TransactionOp::Delete(source.raw()));
+    }
+
+    /// Returns the number of queued changes.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Returns `true` if no changes are queued.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Applies the queued changes in the order they were made.
+    ///
+    /// # Errors
+    ///
+    /// If a change fails, the remaining ones are skipped and the error is returned. Changes
+    /// applied before the failing one are not rolled back.
+    pub fn commit(self) -> io::Result<()> {
+        if self.ops.is_empty() {
+            return Ok(());
+        }
+
+        // Keep new waits from starting, then take the event buffer from any thread in `wait()`.
+        self.poller.commits.fetch_add(1, Ordering::SeqCst);
+        let res = self.poller.wake().and_then(|()| {
+            let _lock = self.poller.events.lock().unwrap();
+            self.apply()
+        });
+        self.poller.commits.fetch_sub(1, Ordering::SeqCst);
+        res
+    }
+
+    fn apply(&self) -> io::Result<()> {
+        for &op in &self.ops {
+            match op {
+                TransactionOp::Add(raw, interest) => self.poller.add(raw, interest)?,
+                TransactionOp::Modify(raw, interest) => self.poller.modify(raw, interest)?,
+                TransactionOp::Delete(raw) => self.poller.delete(raw)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A handle that wakes up a [`Poller`] and is safe to use from a signal handler.
+///
+/// Created by [`Poller::waker_handle()`]. [`wake()`][`WakerHandle::wake()`] has the same effect
+/// as [`Poller::notify()`], but is async-signal-safe: it performs exactly one `write()` of 8 bytes
+/// to the poller's eventfd, ignoring errors, and doesn't allocate, take locks, or log. This makes
+/// it suitable for interrupting [`Poller::wait()`] from an existing signal handler, where
+/// `signalfd` isn't an option.
+///
+/// Only [`wake()`][`WakerHandle::wake()`] is async-signal-safe. Creating, cloning, and dropping
+/// the handle are not, so create it in advance and keep it somewhere the handler can reach, such
+/// as a static. The handle keeps the eventfd open, so it stays valid even if the [`Poller`] is
+/// dropped, in which case waking up does nothing.
+///
+/// Note that a signal handler runs on an arbitrary thread, and the `write()` may change `errno`.
+/// A handler that needs `errno` preserved should save and restore it around the call.
+///
+/// This type is only available on Linux and Android.
+///
+/// # Examples
+///
+/// ```
+/// use polling::Poller;
+/// use std::thread;
+///
+/// let poller = Poller::new()?;
+/// let waker = poller.waker_handle();
+///
+/// // In a real program, `waker.wake()` would be called from a signal handler.
+/// thread::spawn(move || waker.wake());
+///
+/// let mut events = Vec::new();
+/// poller.wait(&mut events, None)?;
+/// assert!(events.is_empty());
+/// # std::io::Result::Ok(())
+/// ```
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug, Clone)]
+pub struct WakerHandle {
+    poller: Arc<sys::Poller>,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl WakerHandle {
+    /// Wakes up the current or the following invocation of [`Poller::wait()`].
+    ///
+    /// This method is async-signal-safe.
+    pub fn wake(&self) {
+        self.poller.notify_signal_safe();
+    }
+}
+
+/// The operating system facility a [`Poller`] is built on.
+///
+/// Exactly one backend is compiled in for each platform, so this mostly serves to check which
+/// one a test or a log message is dealing with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// epoll, on Linux and Android.
+    Epoll,
+    /// Event ports, on illumos and Solaris.
+    EventPorts,
+    /// kqueue, on macOS, iOS and the BSDs.
+    Kqueue,
+    /// poll, on other Unix platforms.
+    Poll,
+    /// wepoll, on Windows.
+    Wepoll,
+}
+
+impl Backend {
+    /// Returns the backends compiled into this build.
+    ///
+    /// A test suite can iterate over them with [`Poller::with_backend()`] to exercise every
+    /// backend available on the platform through the same API.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Backend, Poller};
+    ///
+    /// for &backend in Backend::available() {
+    ///     let poller = Poller::with_backend(backend)?;
+    ///     assert_eq!(poller.backend(), backend);
+    /// }
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn available() -> &'static [Backend] {
+        &[BACKEND]
+    }
+}
+
+/// Configures and creates a [`Poller`].
+///
+/// Created by [`Poller::builder()`].
+#[derive(Debug, Clone)]
+pub struct Builder {
+    timer: bool,
+    software_timer: bool,
+    notify_key: Option<usize>,
+    backend: Option<Backend>,
+    track_registrations: bool,
+}
+
+impl Builder {
+    /// Creates a builder with the default configuration.
+    pub fn new() -> Builder {
+        Builder {
+            timer: true,
+            software_timer: false,
+            notify_key: None,
+            backend: None,
+            track_registrations: false,
+        }
+    }
+
+    /// Records every file descriptor and socket added to the poller.
+    ///
+    /// By default, the poller keeps no bookkeeping of its own, so [`Poller::add()`],
+    /// [`Poller::modify()`], [`Poller::delete()`], and [`Poller::wait()`] cost just their system
+    /// calls. Introspection methods such as [`Poller::contains()`], [`Poller::interest()`], and
+    /// [`Poller::dump_registrations()`] then know about no source at all, and the methods that
+    /// rely on the bookkeeping fail: adding a source with a label, a handler, a rate limit, or
+    /// writable interest on demand, and [`Poller::drain()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    ///
+    /// let source = std::net::TcpListener::bind("127.0.0.1:0")?;
+    /// let poller = Poller::builder().track_registrations().build()?;
+    /// poller.add(&source, Event::readable(7))?;
+    /// assert!(poller.contains(&source));
+    /// # poller.delete(&source)?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn track_registrations(mut self) -> Builder {
+        self.track_registrations = true;
+        self
+    }
+
+    /// Uses the given backend instead of the default one.
+    ///
+    /// # Errors
+    ///
+    /// [`build()`][`Builder::build()`] returns an error if `backend` isn't compiled in, see
+    /// [`Backend::available()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Backend, Poller};
+    ///
+    /// let backend = Backend::available()[0];
+    /// let poller = Poller::builder().backend(backend).build()?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn backend(mut self, backend: Backend) -> Builder {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Doesn't set up an internal timer for precise timeouts.
+    ///
+    /// On Linux and Android, the poller normally creates a timerfd so that timeouts passed to
+    /// [`Poller::wait()`] aren't limited to millisecond precision. A poller that is nested inside
+    /// another reactor and only ever waited on with a zero timeout has no use for it, and this
+    /// option saves a file descriptor and two syscalls per wait. Timeouts then get rounded up to
+    /// a whole millisecond.
+    ///
+    /// On other platforms, this option has no effect.
+    pub fn without_timer(mut self) -> Builder {
+        self.timer = false;
+        self
+    }
+
+    /// Falls back to a thread for precise timeouts.
+    ///
+    /// Where the poller can't wait with a timeout finer than a millisecond, that is, on Linux and
+    /// Android when no timerfd could be created (see [`Poller::timer_fallback_reason()`]) or it
+    /// was disabled with [`without_timer()`][`Builder::without_timer()`], on Windows, and on
+    /// other Unix platforms that use `poll()`, timeouts passed to [`Poller::wait()`] are rounded
+    /// up to a whole millisecond. With this option, the poller starts a thread that sleeps until
+    /// the exact deadline of each blocking wait and then wakes it up like [`Poller::notify()`]
+    /// does.
+    ///
+    /// This costs a thread, which lives as long as the poller, and two context switches per
+    /// wait with a timeout. The precision is that of the thread scheduler, which is typically
+    /// tens of microseconds on an idle system and worse under load. A wake-up that races with
+    /// the end of a wait can make the following wait return early with no events.
+    ///
+    /// Where the poller already has precise timeouts, no thread is started.
+    pub fn software_timer(mut self) -> Builder {
+        self.software_timer = true;
+        self
+    }
+
+    /// Reports notifications as events with the given key.
+    ///
+    /// By default, a wait woken up by [`Poller::notify()`] returns without an event for it. With
+    /// this option, it delivers `Event { key, readable: true, writable: false }` after any I/O
+    /// events and before any expired timers, so a single dispatch loop can handle wakeups like
+    /// any other event. Several notifications before the same wait are reported as one event.
+    /// Wakeups the poller does internally, for example when a new timer is armed, are not
+    /// reported. The key is reserved: adding or modifying a file descriptor or socket with it,
+    /// or arming a timer with it, fails.
+    ///
+    /// # Errors
+    ///
+    /// [`build()`][`Builder::build()`] returns an error if `key` equals `usize::MAX`, because
+    /// that key is reserved for internal use.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use polling::{Event, Poller};
+    ///
+    /// let poller = Poller::builder().notify_as_event(0).build()?;
+    /// poller.notify()?;
+    ///
+    /// let mut events = Vec::new();
+    /// poller.wait(&mut events, None)?;
+    /// assert_eq!(events, [Event::readable(0)]);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn notify_as_event(mut self, key: usize) -> Builder {
+        self.notify_key = Some(key);
+        self
+    }
+
+    /// Creates the poller.
+    pub fn build(self) -> io::Result<Poller> {
+        if self.notify_key == Some(NOTIFY_KEY) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the key is not allowed to be `usize::MAX`",
+            ));
+        }
+        if let Some(backend) = self.backend {
+            if !Backend::available().contains(&backend) {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "the {:?} backend is not available on this platform",
+                        backend
+                    ),
+                ));
+            }
+        }
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let poller = sys::Poller::new(self.timer)?;
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let poller = {
+            let _ = self.timer;
+            sys::Poller::new()?
+        };
+
+        cfg_if! {
+            if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                let precise = poller.has_timer();
+            } else if #[cfg(any(
+                target_os = "illumos",
+                target_os = "solaris",
+                target_os = "macos",
+                target_os = "ios",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd",
+                target_os = "dragonfly",
+            ))] {
+                let precise = true;
+            } else {
+                let precise = false;
+            }
+        }
+
+        let poller = Arc::new(poller);
+        let software_timer = if self.software_timer && !precise {
+            Some(SoftwareTimer::spawn(poller.clone())?)
+        } else {
+            None
+        };
+
+        Ok(Poller {
+            poller,
+            software_timer,
+            events: Mutex::new(sys::Events::new()),
+            notified: AtomicBool::new(false),
+            notify_key: self.notify_key,
+            user_notified: AtomicBool::new(false),
+            draining: AtomicBool::new(false),
+            timers: Mutex::new(BinaryHeap::new()),
+            armed_timers: AtomicUsize::new(0),
+            tracking: self.track_registrations,
+            registrations: Mutex::new(Registrations::default()),
+            last_wake: Mutex::new(None),
+            last_full: AtomicBool::new(false),
+            commits: AtomicUsize::new(0),
+            rearms: Mutex::new(Vec::new()),
+            handlers: Mutex::new(HashMap::new()),
+            #[cfg(feature = "test-util")]
+            clock: None,
+            #[cfg(feature = "metrics")]
+            wait_hook: Mutex::new(None),
+        })
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Builder {
+        Builder::new()
+    }
+}
+
+/// A thread that wakes up the poller at precise deadlines.
+///
+/// See [`Builder::software_timer()`].
+#[derive(Debug)]
+struct SoftwareTimer {
+    shared: Arc<(Mutex<SoftwareTimerState>, Condvar)>,
+}
+
+#[derive(Debug, Default)]
+struct SoftwareTimerState {
+    /// The deadline of the current wait, if it blocks with a timeout.
+    deadline: Option<Instant>,
+    /// Set when the poller is dropped.
+    shutdown: bool,
+}
+
+impl SoftwareTimer {
+    /// Starts the thread.
+    fn spawn(poller: Arc<sys::Poller>) -> io::Result<SoftwareTimer> {
+        let shared = Arc::new((Mutex::new(SoftwareTimerState::default()), Condvar::new()));
+        let state = shared.clone();
+
+        thread::Builder::new()
+            .name("polling-timer".to_string())
+            .spawn(move || {
+                let (lock, cvar) = &*state;
+                let mut state = lock.lock().unwrap();
+                while !state.shutdown {
+                    match state.deadline {
+                        None => state = cvar.wait(state).unwrap(),
+                        Some(deadline) => {
+                            let now = Instant::now();
+                            if deadline > now {
+                                // Wake up early if the deadline changes.
+                                state = cvar.wait_timeout(state, deadline - now).unwrap().0;
+                            } else {
+                                state.deadline = None;
+                                log::trace!("software timer: waking up the poller");
+                                let _ = poller.notify();
+                            }
+                        }
+                    }
+                }
+            })?;
+
+        Ok(SoftwareTimer { shared })
+    }
+
+    /// Sets or clears the deadline of the current wait.
+    fn set(&self, deadline: Option<Instant>) {
+        let (lock, cvar) = &*self.shared;
+        lock.lock().unwrap().deadline = deadline;
+        cvar.notify_one();
+    }
+}
+
+impl Drop for SoftwareTimer {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.shared;
+        lock.lock().unwrap().shutdown = true;
+        cvar.notify_one();
+    }
+}
+
+/// Sets a low-water mark socket option.
+#[cfg(unix)]
+#[allow(clippy::incompatible_msrv)]
+fn set_lowat(fd: RawFd, option: libc::c_int, lowat: usize) -> io::Result<()> {
+    let lowat: libc::c_int = lowat.try_into().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the low-water mark does not fit in a `c_int`",
+        )
+    })?;
+    syscall!(setsockopt(
+        fd,
+        libc::SOL_SOCKET,
+        option,
+        &lowat as *const libc::c_int as *const libc::c_void,
+        std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+    ))?;
+    Ok(())
 }
 
 impl fmt::Debug for Poller {
@@ -451,7 +3541,17 @@ cfg_if! {
     if #[cfg(unix)] {
         use std::os::unix::io::{AsRawFd, RawFd};
 
+        /// The raw handle of an I/O object.
+        type RawSource = RawFd;
+
         /// A [`RawFd`] or a reference to a type implementing [`AsRawFd`].
+        ///
+        /// This includes references to standard library types such as
+        /// [`TcpListener`][`std::net::TcpListener`], [`TcpStream`][`std::net::TcpStream`],
+        /// [`UdpSocket`][`std::net::UdpSocket`] and
+        /// [`UnixStream`][`std::os::unix::net::UnixStream`], which can be passed to the poller
+        /// directly. Remember to put them into non-blocking mode first, or reading and writing
+        /// after an event is delivered may block the whole event loop.
         pub trait Source {
             /// Returns the [`RawFd`] for this I/O object.
             fn raw(&self) -> RawFd;
@@ -471,7 +3571,16 @@ cfg_if! {
     } else if #[cfg(windows)] {
         use std::os::windows::io::{AsRawSocket, RawSocket};
 
+        /// The raw handle of an I/O object.
+        type RawSource = RawSocket;
+
         /// A [`RawSocket`] or a reference to a type implementing [`AsRawSocket`].
+        ///
+        /// This includes references to standard library types such as
+        /// [`TcpListener`][`std::net::TcpListener`], [`TcpStream`][`std::net::TcpStream`] and
+        /// [`UdpSocket`][`std::net::UdpSocket`], which can be passed to the poller directly.
+        /// Remember to put them into non-blocking mode first, or reading and writing after an
+        /// event is delivered may block the whole event loop.
         pub trait Source {
             /// Returns the [`RawSocket`] for this I/O object.
             fn raw(&self) -> RawSocket;
@@ -349,6 +349,13 @@ const READ_REVENTS: libc::c_short = libc::POLLIN | libc::POLLPRI | libc::POLLHUP
 const WRITE_REVENTS: libc::c_short =
     libc::POLLOUT | libc::POLLWRBAND | libc::POLLHUP | libc::POLLERR;
 
+/// Returns the interest left armed after `ev` is delivered to a registration with `interest`.
+///
+/// Delivering any event disables the whole registration.
+pub fn disarm(interest: Event, _ev: Event) -> Event {
+    Event::none(interest.key)
+}
+
 /// A list of reported I/O events.
 pub struct Events {
     inner: Vec<Event>,
@@ -360,6 +367,13 @@ impl Events {
         Self { inner: Vec::new() }
     }
 
+    /// Returns `true` if the last wait filled the whole list.
+    ///
+    /// The list grows to hold every ready file descriptor, so it is never full.
+    pub fn is_full(&self) -> bool {
+        false
+    }
+
     /// Iterates over I/O events.
     pub fn iter(&self) -> impl Iterator<Item = Event> + '_ {
         self.inner.iter().copied()
@@ -160,6 +160,13 @@ fn write_flags() -> libc::c_short {
     libc::POLLOUT | libc::POLLHUP | libc::POLLERR
 }
 
+/// Returns the interest left armed after `ev` is delivered to a registration with `interest`.
+///
+/// Delivering any event disables the whole registration.
+pub fn disarm(interest: Event, _ev: Event) -> Event {
+    Event::none(interest.key)
+}
+
 /// A list of reported I/O events.
 pub struct Events {
     list: Box<[libc::port_event]>,
@@ -183,6 +190,11 @@ impl Events {
         Events { list, len }
     }
 
+    /// Returns `true` if the last wait filled the whole list.
+    pub fn is_full(&self) -> bool {
+        self.len == self.list.len()
+    }
+
     /// Iterates over I/O events.
     pub fn iter(&self) -> impl Iterator<Item = Event> + '_ {
         self.list[..self.len].iter().map(|ev| Event {
@@ -181,6 +181,13 @@ const READ_FLAGS: u32 = we::EPOLLIN | we::EPOLLRDHUP | we::EPOLLHUP | we::EPOLLE
 /// Wepoll flags for all possible writability events.
 const WRITE_FLAGS: u32 = we::EPOLLOUT | we::EPOLLHUP | we::EPOLLERR;
 
+/// Returns the interest left armed after `ev` is delivered to a registration with `interest`.
+///
+/// Delivering any event disables the whole registration.
+pub fn disarm(interest: Event, _ev: Event) -> Event {
+    Event::none(interest.key)
+}
+
 /// A list of reported I/O events.
 pub struct Events {
     list: Box<[we::epoll_event]>,
@@ -202,6 +209,11 @@ impl Events {
         }
     }
 
+    /// Returns `true` if the last wait filled the whole list.
+    pub fn is_full(&self) -> bool {
+        self.len == self.list.len()
+    }
+
     /// Iterates over I/O events.
     pub fn iter(&self) -> impl Iterator<Item = Event> + '_ {
         self.list[..self.len].iter().map(|ev| Event {
@@ -0,0 +1,43 @@
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use polling::{Backend, Event, Poller};
+
+#[test]
+fn each_available() -> io::Result<()> {
+    for &backend in Backend::available() {
+        let poller = Poller::with_backend(backend)?;
+        assert_eq!(poller.backend(), backend);
+
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let stream = TcpStream::connect(listener.local_addr()?)?;
+        poller.add(&stream, Event::writable(1))?;
+
+        let mut events = Vec::new();
+        poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+        assert_eq!(events, [Event::writable(1)], "{:?}", backend);
+        poller.delete(&stream)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn unavailable() {
+    let all = [
+        Backend::Epoll,
+        Backend::EventPorts,
+        Backend::Kqueue,
+        Backend::Poll,
+        Backend::Wepoll,
+    ];
+    for &backend in &all {
+        let available = Backend::available().contains(&backend);
+        assert_eq!(
+            Poller::with_backend(backend).is_ok(),
+            available,
+            "{:?}",
+            backend
+        );
+    }
+}
@@ -0,0 +1,60 @@
+#![cfg(target_os = "linux")]
+
+use std::io::{self, Read};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use polling::{ChildEvent, ChildMonitor, Event, Poller};
+
+#[test]
+fn exit_and_stdout() -> io::Result<()> {
+    let poller = Poller::builder().track_registrations().build()?;
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg("echo hello")
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut monitor = ChildMonitor::new(&poller, &mut child, 10)?;
+    assert_eq!(monitor.key(ChildEvent::Exited), 10);
+    assert_eq!(monitor.key(ChildEvent::Stdout), 11);
+
+    let mut output = Vec::new();
+    let mut status = None;
+    let mut events = Vec::new();
+    while status.is_none() {
+        events.clear();
+        poller.wait(&mut events, Some(Duration::from_secs(5)))?;
+        assert!(!events.is_empty());
+
+        for ev in &events {
+            match monitor.event(ev) {
+                Some(ChildEvent::Exited) => status = monitor.try_reap()?,
+                Some(ChildEvent::Stdout) => {
+                    let mut buf = [0; 64];
+                    let n = monitor.child().stdout.as_mut().unwrap().read(&mut buf)?;
+                    output.extend_from_slice(&buf[..n]);
+                    if n > 0 {
+                        monitor.rearm(ChildEvent::Stdout)?;
+                    }
+                }
+                other => panic!("unexpected event {:?}: {:?}", ev, other),
+            }
+        }
+    }
+
+    assert!(status.unwrap().success());
+    assert_eq!(monitor.event(&Event::readable(12)), None);
+
+    // The rest of the output is still in the pipe.
+    monitor
+        .child()
+        .stdout
+        .as_mut()
+        .unwrap()
+        .read_to_end(&mut output)?;
+    assert_eq!(output, b"hello\n");
+
+    drop(monitor);
+    assert!(poller.dump_registrations().is_empty());
+    Ok(())
+}
@@ -61,6 +61,39 @@ fn concurrent_modify() -> io::Result<()> {
     Ok(())
 }
 
+#[test]
+fn concurrent_commit() -> io::Result<()> {
+    let (a, b) = tcp_pair()?;
+    let poller = Poller::new()?;
+
+    let mut events = Vec::new();
+
+    Parallel::new()
+        .add(|| {
+            // The commit wakes this thread up, possibly with no events.
+            while events.is_empty() {
+                poller.wait(&mut events, None)?;
+            }
+            Ok(())
+        })
+        .add(|| {
+            thread::sleep(Duration::from_millis(100));
+            let mut txn = poller.begin();
+            txn.add(&a, Event::writable(0));
+            txn.add(&b, Event::writable(1));
+            txn.commit()
+        })
+        .run()
+        .into_iter()
+        .collect::<io::Result<()>>()?;
+
+    // Both additions were in place before the wait fetched events.
+    events.sort_by_key(|ev| ev.key);
+    assert_eq!(events, [Event::writable(0), Event::writable(1)]);
+
+    Ok(())
+}
+
 fn tcp_pair() -> io::Result<(TcpStream, TcpStream)> {
     let listener = TcpListener::bind("127.0.0.1:0")?;
     let a = TcpStream::connect(listener.local_addr()?)?;
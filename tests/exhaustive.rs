@@ -0,0 +1,77 @@
+#![cfg(any(target_os = "linux", target_os = "android"))]
+
+use std::io;
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use polling::{Event, Poller};
+
+/// More ready file descriptors than fit in the poller's event buffer.
+const SOURCES: usize = 1100;
+
+/// Raises the soft limit on open files so that `n` more can be opened.
+///
+/// Returns `false` if the hard limit is too low.
+fn reserve_fds(n: usize) -> io::Result<bool> {
+    // Leave room for the descriptors the test harness and the poller already use.
+    let wanted = (n + 64) as libc::rlim_t;
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    if limit.rlim_cur >= wanted {
+        return Ok(true);
+    }
+    if limit.rlim_max < wanted {
+        return Ok(false);
+    }
+    limit.rlim_cur = wanted;
+    if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(true)
+}
+
+#[test]
+fn collects_more_than_one_batch() -> io::Result<()> {
+    if !reserve_fds(SOURCES)? {
+        eprintln!(
+            "skipping: the open file limit is too low for {} sources",
+            SOURCES
+        );
+        return Ok(());
+    }
+    let (a, _b) = UnixStream::pair()?;
+
+    let poller = Poller::new()?;
+    let clones = (0..SOURCES)
+        .map(|_| a.try_clone())
+        .collect::<io::Result<Vec<_>>>()?;
+    for (key, clone) in clones.iter().enumerate() {
+        poller.add(clone, Event::writable(key))?;
+    }
+
+    let mut events = Vec::new();
+    let n = poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert!(n < clones.len());
+    assert!(poller.last_wait_was_full());
+
+    for (key, clone) in clones.iter().enumerate() {
+        poller.modify(clone, Event::writable(key))?;
+    }
+
+    events.clear();
+    let n = poller.wait_exhaustive(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(n, clones.len());
+    assert!(!poller.last_wait_was_full());
+
+    events.sort_by_key(|ev| ev.key);
+    for (key, ev) in events.iter().enumerate() {
+        assert_eq!(*ev, Event::writable(key));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,318 @@
+#[cfg(unix)]
+use std::fs::File;
+use std::io::{self, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::io::FromRawFd;
+use std::time::{Duration, Instant};
+
+use polling::{Event, Poller, Source};
+
+/// Something done to a connected pair of I/O objects before waiting on the first one.
+type Action<T> = fn(&T, &mut Option<T>) -> io::Result<()>;
+
+/// Something done to a poller before waiting on it.
+type TimerAction = fn(&Poller) -> io::Result<()>;
+
+fn idle<T>(_: &T, _: &mut Option<T>) -> io::Result<()> {
+    Ok(())
+}
+
+fn peer_writes<T: Write>(_: &T, peer: &mut Option<T>) -> io::Result<()> {
+    peer.as_mut().unwrap().write_all(&[1])
+}
+
+fn peer_shuts_down_write(_: &TcpStream, peer: &mut Option<TcpStream>) -> io::Result<()> {
+    peer.as_ref().unwrap().shutdown(Shutdown::Write)
+}
+
+fn peer_closes<T>(_: &T, peer: &mut Option<T>) -> io::Result<()> {
+    peer.take();
+    Ok(())
+}
+
+fn shuts_down_read(socket: &TcpStream, _: &mut Option<TcpStream>) -> io::Result<()> {
+    socket.shutdown(Shutdown::Read)
+}
+
+fn shuts_down_write(socket: &TcpStream, _: &mut Option<TcpStream>) -> io::Result<()> {
+    socket.shutdown(Shutdown::Write)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn shuts_down_both(socket: &TcpStream, _: &mut Option<TcpStream>) -> io::Result<()> {
+    socket.shutdown(Shutdown::Both)
+}
+
+#[test]
+fn tcp() -> io::Result<()> {
+    let matrix: &[(&str, Event, Action<TcpStream>, &[Event])] = &[
+        ("readable, idle", Event::readable(1), idle, &[]),
+        (
+            "writable, idle",
+            Event::writable(1),
+            idle,
+            &[Event::writable(1)],
+        ),
+        ("all, idle", Event::all(1), idle, &[Event::writable(1)]),
+        ("none, idle", Event::none(1), idle, &[]),
+        (
+            "readable, peer writes",
+            Event::readable(1),
+            peer_writes,
+            &[Event::readable(1)],
+        ),
+        (
+            "writable, peer writes",
+            Event::writable(1),
+            peer_writes,
+            &[Event::writable(1)],
+        ),
+        ("none, peer writes", Event::none(1), peer_writes, &[]),
+        // End of stream is readable, so the next read returns 0.
+        (
+            "readable, peer shuts down writing",
+            Event::readable(1),
+            peer_shuts_down_write,
+            &[Event::readable(1)],
+        ),
+        (
+            "writable, peer shuts down writing",
+            Event::writable(1),
+            peer_shuts_down_write,
+            &[Event::writable(1)],
+        ),
+        (
+            "readable, peer closes",
+            Event::readable(1),
+            peer_closes,
+            &[Event::readable(1)],
+        ),
+        (
+            "writable, peer closes",
+            Event::writable(1),
+            peer_closes,
+            &[Event::writable(1)],
+        ),
+        (
+            "readable, shuts down reading",
+            Event::readable(1),
+            shuts_down_read,
+            &[Event::readable(1)],
+        ),
+        (
+            "writable, shuts down writing",
+            Event::writable(1),
+            shuts_down_write,
+            &[Event::writable(1)],
+        ),
+    ];
+    check(matrix, tcp_pair)
+}
+
+// With epoll, a hangup (both directions shut down) is reported regardless of interest, and as
+// both readable and writable. The peer closing only ends the stream in one direction.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn tcp_hangup() -> io::Result<()> {
+    let matrix: &[(&str, Event, Action<TcpStream>, &[Event])] = &[
+        ("none, peer closes", Event::none(1), peer_closes, &[]),
+        (
+            "readable, shuts down both",
+            Event::readable(1),
+            shuts_down_both,
+            &[Event::all(1)],
+        ),
+        (
+            "writable, shuts down both",
+            Event::writable(1),
+            shuts_down_both,
+            &[Event::all(1)],
+        ),
+        (
+            "none, shuts down both",
+            Event::none(1),
+            shuts_down_both,
+            &[Event::all(1)],
+        ),
+    ];
+    check(matrix, tcp_pair)
+}
+
+#[cfg(unix)]
+#[test]
+fn pipe_reader() -> io::Result<()> {
+    let matrix: &[(&str, Event, Action<File>, &[Event])] = &[
+        ("readable, idle", Event::readable(1), idle, &[]),
+        ("writable, idle", Event::writable(1), idle, &[]),
+        (
+            "readable, writer writes",
+            Event::readable(1),
+            peer_writes,
+            &[Event::readable(1)],
+        ),
+        (
+            "writable, writer writes",
+            Event::writable(1),
+            peer_writes,
+            &[],
+        ),
+    ];
+    check(matrix, pipe)
+}
+
+#[cfg(unix)]
+#[test]
+fn pipe_writer() -> io::Result<()> {
+    let matrix: &[(&str, Event, Action<File>, &[Event])] = &[
+        ("readable, idle", Event::readable(1), idle, &[]),
+        (
+            "writable, idle",
+            Event::writable(1),
+            idle,
+            &[Event::writable(1)],
+        ),
+    ];
+    check(matrix, || pipe().map(|(reader, writer)| (writer, reader)))
+}
+
+// With epoll, the writer closing hangs up the reader and the reader closing is an error for the
+// writer. Both are reported regardless of interest, and as both readable and writable.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn pipe_hangup() -> io::Result<()> {
+    let matrix: &[(&str, Event, Action<File>, &[Event])] = &[
+        (
+            "readable, writer closes",
+            Event::readable(1),
+            peer_closes,
+            &[Event::all(1)],
+        ),
+        (
+            "writable, writer closes",
+            Event::writable(1),
+            peer_closes,
+            &[Event::all(1)],
+        ),
+        (
+            "none, writer closes",
+            Event::none(1),
+            peer_closes,
+            &[Event::all(1)],
+        ),
+    ];
+    check(matrix, pipe)?;
+
+    let matrix: &[(&str, Event, Action<File>, &[Event])] = &[
+        (
+            "writable, reader closes",
+            Event::writable(1),
+            peer_closes,
+            &[Event::all(1)],
+        ),
+        (
+            "readable, reader closes",
+            Event::readable(1),
+            peer_closes,
+            &[Event::all(1)],
+        ),
+        (
+            "none, reader closes",
+            Event::none(1),
+            peer_closes,
+            &[Event::all(1)],
+        ),
+    ];
+    check(matrix, || pipe().map(|(reader, writer)| (writer, reader)))
+}
+
+fn arms_elapsed(poller: &Poller) -> io::Result<()> {
+    poller.arm_timer(1, Instant::now())
+}
+
+fn arms_two_elapsed(poller: &Poller) -> io::Result<()> {
+    let now = Instant::now();
+    poller.arm_timer(2, now)?;
+    poller.arm_timer(1, now - Duration::from_millis(1))
+}
+
+fn arms_future(poller: &Poller) -> io::Result<()> {
+    poller.arm_timer(1, Instant::now() + Duration::from_secs(10))
+}
+
+fn notifies(poller: &Poller) -> io::Result<()> {
+    poller.notify()
+}
+
+// Timers are readable, and are reported in deadline order. Notifications deliver no events.
+#[test]
+fn timer_and_notify() -> io::Result<()> {
+    let matrix: &[(&str, TimerAction, &[Event])] = &[
+        ("elapsed timer", arms_elapsed, &[Event::readable(1)]),
+        (
+            "two elapsed timers",
+            arms_two_elapsed,
+            &[Event::readable(1), Event::readable(2)],
+        ),
+        ("future timer", arms_future, &[]),
+        ("notification", notifies, &[]),
+    ];
+    for &(name, action, expected) in matrix {
+        let poller = Poller::new()?;
+        action(&poller)?;
+
+        // Arming a timer may wake the poller without an event, so keep waiting for a while.
+        let start = Instant::now();
+        let mut events = Vec::new();
+        while events.len() < expected.len() && start.elapsed() < Duration::from_secs(1) {
+            poller.wait(&mut events, Some(Duration::from_millis(100)))?;
+        }
+        poller.wait(&mut events, Some(Duration::from_millis(0)))?;
+        assert_eq!(events, expected, "{}", name);
+    }
+    Ok(())
+}
+
+fn check<T>(
+    matrix: &[(&str, Event, Action<T>, &[Event])],
+    pair: fn() -> io::Result<(T, T)>,
+) -> io::Result<()>
+where
+    for<'a> &'a T: Source,
+{
+    for &(name, interest, action, expected) in matrix {
+        let (socket, peer) = pair()?;
+        let mut peer = Some(peer);
+
+        let poller = Poller::new()?;
+        poller.add(&socket, interest)?;
+        action(&socket, &mut peer)?;
+
+        // Block until the expected event arrives, or long enough to see an unexpected one.
+        let timeout = if expected.is_empty() {
+            Duration::from_millis(100)
+        } else {
+            Duration::from_secs(1)
+        };
+        let mut events = Vec::new();
+        poller.wait(&mut events, Some(timeout))?;
+        assert_eq!(events, expected, "{}", name);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn pipe() -> io::Result<(File, File)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    unsafe { Ok((File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1]))) }
+}
+
+fn tcp_pair() -> io::Result<(TcpStream, TcpStream)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let a = TcpStream::connect(listener.local_addr()?)?;
+    let (b, _) = listener.accept()?;
+    Ok((a, b))
+}
@@ -0,0 +1,621 @@
+use std::io::{self, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use polling::{Event, Poller};
+
+#[test]
+fn disarmed_after_event() -> io::Result<()> {
+    let (reader, mut writer) = tcp_pair()?;
+    let poller = tracked()?;
+    let mut events = Vec::new();
+
+    poller.add(&reader, Event::readable(1))?;
+    assert!(poller.contains(&reader));
+    assert_eq!(poller.interest(&reader), Some(Event::readable(1)));
+
+    // Deliver a readable event.
+    writer.write_all(&[1])?;
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events, [Event::readable(1)]);
+    assert_eq!(poller.interest(&reader), Some(Event::none(1)));
+
+    // Re-arm it.
+    poller.modify(&reader, Event::readable(2))?;
+    assert_eq!(poller.interest(&reader), Some(Event::readable(2)));
+
+    // Not delivered again until it is re-armed.
+    poller.modify(&reader, Event::none(2))?;
+    events.clear();
+    poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    assert!(events.is_empty());
+    assert_eq!(poller.interest(&reader), Some(Event::none(2)));
+
+    poller.delete(&reader)?;
+    assert!(!poller.contains(&reader));
+    assert_eq!(poller.interest(&reader), None);
+
+    Ok(())
+}
+
+#[test]
+fn untracked() -> io::Result<()> {
+    let (a, b) = tcp_pair()?;
+    let poller = Poller::new()?;
+    let mut events = Vec::new();
+
+    // Plain sources aren't recorded.
+    poller.add(&a, Event::writable(1))?;
+    assert!(!poller.contains(&a));
+    assert!(poller.dump_registrations().is_empty());
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events, [Event::writable(1)]);
+    poller.modify(&a, Event::writable(1))?;
+
+    // Methods relying on the bookkeeping fail without adding anything.
+    assert!(poller.add_with_label(&b, Event::none(2), "b").is_err());
+    assert!(poller
+        .add_with_rate_limit(&b, Event::none(2), Duration::from_millis(1))
+        .is_err());
+    assert!(poller.add_with_handler(&b, Event::none(2), |_| {}).is_err());
+    assert!(poller.add_writable_on_demand(&b, 2).is_err());
+    assert!(poller.drain(Instant::now(), |_| Ok(())).is_err());
+    poller.add(&b, Event::none(2))?;
+
+    // Counts don't ignore the sources.
+    assert!(poller.recommended_events_capacity() >= 2);
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let tracked = tracked()?;
+        assert_eq!(poller.epoll_watches(), tracked.epoll_watches() + 2);
+    }
+
+    poller.delete(&a)?;
+    poller.delete(&b)?;
+
+    Ok(())
+}
+
+#[test]
+fn rearm_many() -> io::Result<()> {
+    let (a, mut a_writer) = tcp_pair()?;
+    let (b, mut b_writer) = tcp_pair()?;
+    let poller = tracked()?;
+    let mut events = Vec::new();
+
+    poller.add(&a, Event::readable(1))?;
+    poller.add(&b, Event::readable(2))?;
+    a_writer.write_all(&[1])?;
+    b_writer.write_all(&[1])?;
+
+    while events.len() < 2 {
+        poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    }
+    assert_eq!(poller.interest(&a), Some(Event::none(1)));
+    assert_eq!(poller.interest(&b), Some(Event::none(2)));
+
+    poller.rearm_many(&[&a, &b])?;
+    assert_eq!(poller.interest(&a), Some(Event::readable(1)));
+    assert_eq!(poller.interest(&b), Some(Event::readable(2)));
+
+    // The data is still there, so both are delivered again.
+    events.clear();
+    while events.len() < 2 {
+        poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    }
+    events.sort_by_key(|ev| ev.key);
+    assert_eq!(events, [Event::readable(1), Event::readable(2)]);
+
+    // Sources that aren't in the poller are reported, but don't stop the others.
+    poller.delete(&a)?;
+    let err = poller.rearm_many(&[&a, &b]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert_eq!(poller.interest(&b), Some(Event::readable(2)));
+
+    Ok(())
+}
+
+#[test]
+fn rate_limit() -> io::Result<()> {
+    let (reader, mut writer) = tcp_pair()?;
+    let poller = tracked()?;
+    let mut events = Vec::new();
+
+    let cooldown = Duration::from_millis(200);
+    poller.add_with_rate_limit(&reader, Event::readable(1), cooldown)?;
+    writer.write_all(&[1])?;
+
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events, [Event::readable(1)]);
+    let reported = Instant::now();
+
+    // Re-arming during the cooldown is deferred.
+    poller.modify(&reader, Event::readable(1))?;
+    assert_eq!(poller.interest(&reader), Some(Event::none(1)));
+    events.clear();
+    poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    assert!(events.is_empty());
+
+    // The wait wakes up at the end of the cooldown to re-arm it.
+    while events.is_empty() {
+        poller.wait(&mut events, Some(Duration::from_secs(10)))?;
+    }
+    assert_eq!(events, [Event::readable(1)]);
+    assert!(reported.elapsed() >= cooldown - Duration::from_millis(10));
+    assert!(reported.elapsed() < Duration::from_secs(5));
+
+    // Disabling interest is not deferred.
+    poller.modify(&reader, Event::none(1))?;
+    assert_eq!(poller.interest(&reader), Some(Event::none(1)));
+
+    Ok(())
+}
+
+#[test]
+fn rate_limit_stale() -> io::Result<()> {
+    let (stale, mut stale_writer) = tcp_pair()?;
+    let (reader, mut writer) = tcp_pair()?;
+    let poller = tracked()?;
+    let mut events = Vec::new();
+
+    let cooldown = Duration::from_millis(100);
+    poller.add_with_rate_limit(&stale, Event::readable(1), cooldown)?;
+    poller.add_with_rate_limit(&reader, Event::readable(2), cooldown)?;
+    stale_writer.write_all(&[1])?;
+    writer.write_all(&[1])?;
+
+    while events.len() < 2 {
+        poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    }
+    poller.modify(&stale, Event::readable(1))?;
+    poller.modify(&reader, Event::readable(2))?;
+
+    // Closing a source without deleting it makes its deferred re-arm fail.
+    drop(stale);
+
+    // The failed re-arm is dropped and the other one still goes through.
+    events.clear();
+    while events.is_empty() {
+        poller.wait(&mut events, Some(Duration::from_secs(10)))?;
+    }
+    assert_eq!(events, [Event::readable(2)]);
+
+    Ok(())
+}
+
+#[test]
+fn writable_on_demand() -> io::Result<()> {
+    let (a, _b) = tcp_pair()?;
+    let poller = tracked()?;
+    let mut events = Vec::new();
+
+    poller.add_writable_on_demand(&a, 1)?;
+    poller.wait(&mut events, Some(Duration::from_millis(50)))?;
+    assert!(events.is_empty());
+
+    // Writable interest can't be enabled by accident.
+    poller.modify(&a, Event::all(1))?;
+    assert_eq!(poller.interest(&a), Some(Event::readable(1)));
+    poller.wait(&mut events, Some(Duration::from_millis(50)))?;
+    assert!(events.is_empty());
+
+    poller.want_write(&a)?;
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events, [Event::writable(1)]);
+
+    // Still wanting to write, so re-enabling interest includes writability.
+    poller.modify(&a, Event::readable(1))?;
+    assert_eq!(poller.interest(&a), Some(Event::all(1)));
+
+    poller.write_drained(&a)?;
+    assert_eq!(poller.interest(&a), Some(Event::readable(1)));
+
+    // Only managed sources can use this.
+    let (c, _d) = tcp_pair()?;
+    poller.add(&c, Event::readable(2))?;
+    assert!(poller.want_write(&c).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn timer_with_same_key() -> io::Result<()> {
+    let (reader, _writer) = tcp_pair()?;
+    let poller = tracked()?;
+    let mut events = Vec::new();
+
+    poller.add(&reader, Event::readable(1))?;
+    poller.arm_timer(1, Instant::now())?;
+    while events.is_empty() {
+        poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    }
+
+    // Timers don't disarm file descriptors with the same key.
+    assert_eq!(events, [Event::readable(1)]);
+    assert_eq!(poller.interest(&reader), Some(Event::readable(1)));
+
+    Ok(())
+}
+
+#[test]
+fn tiers() -> io::Result<()> {
+    let (a, mut a_writer) = tcp_pair()?;
+    let (b, mut b_writer) = tcp_pair()?;
+    let poller = tracked()?;
+    let mut events = Vec::new();
+
+    poller.add(&a, Event::readable(1))?;
+    poller.add(&b, Event::readable(2))?;
+    poller.set_tier(&b, 1)?;
+
+    a_writer.write_all(&[1])?;
+    b_writer.write_all(&[1])?;
+    while events.len() < 2 {
+        poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    }
+
+    let tier0 = poller.iter_tier(&events, 0).collect::<Vec<_>>();
+    let tier1 = poller.iter_tier(&events, 1).collect::<Vec<_>>();
+    assert_eq!(tier0, [Event::readable(1)]);
+    assert_eq!(tier1, [Event::readable(2)]);
+    assert_eq!(poller.iter_tier(&events, 2).count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn distinct_sources() -> io::Result<()> {
+    let (a, b) = tcp_pair()?;
+    let poller = tracked()?;
+    let mut events = Vec::new();
+
+    poller.add(&a, Event::writable(1))?;
+    poller.add(&b, Event::writable(2))?;
+    poller.arm_timer(3, Instant::now())?;
+    while events.len() < 3 {
+        poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    }
+
+    // Accumulate a second report of the same sources.
+    poller.modify(&a, Event::writable(1))?;
+    poller.modify(&b, Event::writable(2))?;
+    poller.arm_timer(3, Instant::now())?;
+    while events.len() < 6 {
+        poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    }
+
+    assert_eq!(events.len(), 6);
+    assert_eq!(poller.distinct_sources(&events), 3);
+
+    Ok(())
+}
+
+#[test]
+fn dispatch() -> io::Result<()> {
+    let (a, _a_peer) = tcp_pair()?;
+    let (b, _b_peer) = tcp_pair()?;
+    let poller = tracked()?;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = seen.clone();
+    poller.add_with_handler(&a, Event::writable(1), move |ev| {
+        log.lock().unwrap().push(ev)
+    })?;
+    let log = seen.clone();
+    poller.add_with_handler(&b, Event::writable(2), move |ev| {
+        log.lock().unwrap().push(ev)
+    })?;
+    poller.arm_timer(3, Instant::now())?;
+
+    let mut unhandled = Vec::new();
+    let mut called = 0;
+    while called < 2 || unhandled.is_empty() {
+        called += poller.dispatch(&mut unhandled, Some(Duration::from_secs(1)))?;
+    }
+    assert_eq!(called, 2);
+    assert_eq!(unhandled, [Event::readable(3)]);
+
+    let mut seen_events = seen.lock().unwrap().clone();
+    seen_events.sort_by_key(|ev| ev.key);
+    assert_eq!(seen_events, [Event::writable(1), Event::writable(2)]);
+
+    // Deleting a source drops its handler.
+    poller.delete(&a)?;
+    poller.modify(&b, Event::writable(2))?;
+    assert_eq!(Arc::strong_count(&seen), 2);
+    unhandled.clear();
+    assert_eq!(
+        poller.dispatch(&mut unhandled, Some(Duration::from_secs(1)))?,
+        1
+    );
+
+    Ok(())
+}
+
+// A file descriptor closed without being deleted, whose number is reused for a new one added
+// with the same key.
+#[cfg(unix)]
+#[test]
+fn reused_fd() -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let poller = tracked()?;
+    let (a, _a_peer) = tcp_pair()?;
+    let fd = a.as_raw_fd();
+    poller.add(&a, Event::readable(5))?;
+    drop(a);
+
+    // Other tests open file descriptors concurrently, so the number may take a few tries.
+    let mut others = Vec::new();
+    let (b, _b_peer) = loop {
+        let (b, b_peer) = tcp_pair()?;
+        if b.as_raw_fd() == fd {
+            break (b, b_peer);
+        }
+        others.push(b);
+        if others.len() > 100 {
+            // The number was taken by another test for good.
+            return Ok(());
+        }
+    };
+
+    let called = Arc::new(Mutex::new(0));
+    let count = called.clone();
+    poller.add_with_handler(&b, Event::writable(5), move |_| *count.lock().unwrap() += 1)?;
+    assert_eq!(poller.interest(&b), Some(Event::writable(5)));
+
+    let mut unhandled = Vec::new();
+    assert_eq!(
+        poller.dispatch(&mut unhandled, Some(Duration::from_secs(1)))?,
+        1
+    );
+    assert!(unhandled.is_empty());
+    assert_eq!(*called.lock().unwrap(), 1);
+
+    // The delivered event disarmed the new registration.
+    assert_eq!(poller.interest(&b), Some(Event::none(5)));
+
+    Ok(())
+}
+
+#[test]
+fn registration_guard() -> io::Result<()> {
+    let (reader, mut writer) = tcp_pair()?;
+    let poller = tracked()?;
+    let mut events = Vec::new();
+
+    let registration = poller.register(&reader, Event::none(1))?;
+    assert!(poller.contains(&reader));
+    registration.modify(Event::readable(1))?;
+
+    writer.write_all(&[1])?;
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events, [Event::readable(1)]);
+
+    drop(registration);
+    assert!(!poller.contains(&reader));
+
+    // Deleted from the operating system's poller too, so it can be added again.
+    let registration = poller.register(&reader, Event::readable(2))?;
+    registration.delete()?;
+    assert!(!poller.contains(&reader));
+
+    Ok(())
+}
+
+#[test]
+fn registration_group() -> io::Result<()> {
+    let (a, b) = tcp_pair()?;
+    let poller = tracked()?;
+
+    let mut group = poller.registration_group();
+    group.add(&a, Event::readable(1))?;
+    group.add(&b, Event::readable(2))?;
+    assert!(group.add(&a, Event::readable(std::usize::MAX)).is_err());
+    assert_eq!(group.len(), 2);
+
+    drop(group);
+    assert!(!poller.contains(&a));
+    assert!(!poller.contains(&b));
+
+    // Deleted from the operating system's poller too, so they can be added again.
+    let mut group = poller.registration_group();
+    group.add(&a, Event::readable(1))?;
+    group.add(&b, Event::readable(2))?;
+    group.delete()?;
+    assert!(!poller.contains(&a));
+    assert!(!poller.contains(&b));
+
+    Ok(())
+}
+
+#[test]
+fn transaction() -> io::Result<()> {
+    let (a, b) = tcp_pair()?;
+    let poller = tracked()?;
+    poller.add(&a, Event::readable(1))?;
+
+    // Nothing changes until the commit, and changes are applied in order.
+    let mut txn = poller.begin();
+    txn.delete(&a);
+    txn.add(&b, Event::readable(2));
+    txn.modify(&b, Event::writable(2));
+    assert_eq!(txn.len(), 3);
+    assert!(poller.contains(&a));
+    assert!(!poller.contains(&b));
+    txn.commit()?;
+    assert!(!poller.contains(&a));
+    assert_eq!(poller.interest(&b), Some(Event::writable(2)));
+
+    // Dropping discards the changes.
+    let mut txn = poller.begin();
+    txn.delete(&b);
+    drop(txn);
+    assert!(poller.contains(&b));
+
+    // A failing change stops the commit, without undoing earlier ones.
+    let mut txn = poller.begin();
+    txn.add(&a, Event::readable(1));
+    txn.add(&b, Event::readable(2));
+    txn.delete(&b);
+    assert!(txn.commit().is_err());
+    assert!(poller.contains(&a));
+    assert!(poller.contains(&b));
+
+    Ok(())
+}
+
+#[test]
+fn dump() -> io::Result<()> {
+    let (a, mut a_writer) = tcp_pair()?;
+    let (b, _b_writer) = tcp_pair()?;
+    let poller = tracked()?;
+    let mut events = Vec::new();
+
+    poller.add_with_label(&a, Event::readable(1), "a")?;
+    poller.add(&b, Event::readable(2))?;
+    poller.set_tier(&b, 3)?;
+
+    a_writer.write_all(&[1])?;
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events, [Event::readable(1)]);
+
+    let mut infos = poller.dump_registrations();
+    infos.sort_by_key(|info| info.registered.key);
+    assert_eq!(infos.len(), 2);
+
+    assert_eq!(infos[0].registered, Event::readable(1));
+    assert_eq!(infos[0].armed, Event::none(1));
+    assert_eq!(infos[0].label, Some("a"));
+    assert_eq!(infos[0].tier, 0);
+
+    assert_eq!(infos[1].registered, Event::readable(2));
+    assert_eq!(infos[1].armed, Event::readable(2));
+    assert_eq!(infos[1].label, None);
+    assert_eq!(infos[1].tier, 3);
+
+    Ok(())
+}
+
+#[test]
+fn drain() -> io::Result<()> {
+    let (a, mut a_writer) = tcp_pair()?;
+    let (b, _b_writer) = tcp_pair()?;
+    let poller = tracked()?;
+
+    poller.add(&a, Event::readable(1))?;
+    a_writer.write_all(&[1])?;
+
+    // Deleted by the handler once it becomes readable.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let drained = poller.drain(deadline, |ev| {
+        assert_eq!(ev, Event::readable(1));
+        poller.delete(&a)
+    })?;
+    assert!(drained);
+
+    // New registrations are rejected.
+    assert!(poller.add(&b, Event::readable(2)).is_err());
+    assert!(!poller.contains(&b));
+
+    Ok(())
+}
+
+#[test]
+fn drain_while_another_thread_waits() -> io::Result<()> {
+    let (a, mut a_writer) = tcp_pair()?;
+    let poller = Arc::new(tracked()?);
+    poller.add(&a, Event::readable(1))?;
+
+    // The other thread handles the event and deletes the source.
+    let waiter = {
+        let poller = poller.clone();
+        thread::spawn(move || -> io::Result<()> {
+            let mut events = Vec::new();
+            while events.is_empty() {
+                poller.wait(&mut events, Some(Duration::from_secs(10)))?;
+            }
+            poller.delete(&a)
+        })
+    };
+    thread::sleep(Duration::from_millis(50));
+    a_writer.write_all(&[1])?;
+
+    let drained = poller.drain(Instant::now() + Duration::from_secs(5), |ev| {
+        panic!("unexpected event: {:?}", ev)
+    })?;
+    assert!(drained);
+    waiter.join().unwrap()
+}
+
+#[test]
+fn drain_deadline() -> io::Result<()> {
+    let (a, _a_writer) = tcp_pair()?;
+    let poller = tracked()?;
+
+    poller.add(&a, Event::readable(1))?;
+
+    let start = Instant::now();
+    let drained = poller.drain(start + Duration::from_millis(100), |_| Ok(()))?;
+    assert!(!drained);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert!(poller.contains(&a));
+
+    Ok(())
+}
+
+#[test]
+fn recommended_events_capacity() -> io::Result<()> {
+    let (a, b) = tcp_pair()?;
+    let poller = tracked()?;
+    assert_eq!(poller.recommended_events_capacity(), 0);
+
+    poller.add(&a, Event::readable(1))?;
+    poller.add(&b, Event::readable(2))?;
+    poller.arm_timer(3, Instant::now() + Duration::from_secs(10))?;
+    assert_eq!(poller.recommended_events_capacity(), 3);
+
+    poller.delete(&a)?;
+    assert_eq!(poller.recommended_events_capacity(), 2);
+
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn epoll_watches() -> io::Result<()> {
+    let (a, b) = tcp_pair()?;
+    let poller = tracked()?;
+    let timer = poller.timer_fallback_reason().is_none() as usize;
+    assert_eq!(poller.epoll_watches(), 1 + timer);
+
+    let poller = Poller::builder()
+        .without_timer()
+        .track_registrations()
+        .build()?;
+    assert_eq!(poller.epoll_watches(), 1);
+    poller.add(&a, Event::readable(1))?;
+    poller.add(&b, Event::readable(2))?;
+    assert_eq!(poller.epoll_watches(), 3);
+    poller.delete(&a)?;
+    assert_eq!(poller.epoll_watches(), 2);
+
+    // The limit is a positive number wherever `/proc` is mounted.
+    if let Ok(max) = polling::epoll_max_user_watches() {
+        assert!(max > 0);
+    }
+    Ok(())
+}
+
+fn tcp_pair() -> io::Result<(TcpStream, TcpStream)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let a = TcpStream::connect(listener.local_addr()?)?;
+    let (b, _) = listener.accept()?;
+    Ok((a, b))
+}
+
+fn tracked() -> io::Result<Poller> {
+    Poller::builder().track_registrations().build()
+}
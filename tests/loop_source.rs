@@ -0,0 +1,91 @@
+#![cfg(any(target_os = "linux", target_os = "android"))]
+
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use polling::{Event, Poller};
+
+#[test]
+fn driven_by_host() -> io::Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let stream = TcpStream::connect(listener.local_addr()?)?;
+
+    let poller = Poller::builder().track_registrations().build()?;
+    let mut source = poller.loop_source();
+    assert_eq!(source.prepare(), None);
+    assert!(!source.check()?);
+
+    // The host loop is another poller watching the file descriptor.
+    let host = Poller::new()?;
+    let mut host_events = Vec::new();
+    host.add(&source, Event::readable(0))?;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let seen2 = seen.clone();
+    poller.add_with_handler(&stream, Event::writable(1), move |ev| {
+        seen2.lock().unwrap().push(ev);
+    })?;
+
+    host.wait(&mut host_events, Some(Duration::from_secs(1)))?;
+    assert_eq!(host_events, [Event::readable(0)]);
+    assert!(source.check()?);
+    assert_eq!(source.prepare(), Some(Duration::from_secs(0)));
+
+    let mut unhandled = Vec::new();
+    assert_eq!(source.dispatch(&mut unhandled), 1);
+    assert!(unhandled.is_empty());
+    assert_eq!(*seen.lock().unwrap(), [Event::writable(1)]);
+
+    // Once checked, the file descriptor is no longer readable.
+    host_events.clear();
+    host.modify(&source, Event::readable(0))?;
+    host.wait(&mut host_events, Some(Duration::from_millis(50)))?;
+    assert!(host_events.is_empty());
+
+    // Arming the nearest timer wakes the host up to call `prepare()` again.
+    poller.arm_timer(2, Instant::now() + Duration::from_millis(50))?;
+    host.modify(&source, Event::readable(0))?;
+    host.wait(&mut host_events, Some(Duration::from_secs(1)))?;
+    assert_eq!(host_events, [Event::readable(0)]);
+    assert!(!source.check()?);
+
+    // Expiring only shows up in the timeout.
+    host_events.clear();
+    host.modify(&source, Event::readable(0))?;
+    let timeout = source.prepare().unwrap();
+    assert!(timeout <= Duration::from_millis(50));
+    host.wait(&mut host_events, Some(timeout))?;
+    assert!(host_events.is_empty());
+    assert!(source.check()?);
+    source.dispatch(&mut unhandled);
+    assert_eq!(unhandled, [Event::readable(2)]);
+
+    host.delete(&source)?;
+    Ok(())
+}
+
+#[test]
+fn notify_wakes_host() -> io::Result<()> {
+    let poller = Poller::new()?;
+    let mut source = poller.loop_source();
+
+    let host = Poller::new()?;
+    let mut host_events = Vec::new();
+    host.add(&source, Event::readable(0))?;
+
+    poller.notify()?;
+    host.wait(&mut host_events, Some(Duration::from_secs(1)))?;
+    assert_eq!(host_events, [Event::readable(0)]);
+
+    // The notification delivers no event, and is consumed.
+    assert!(!source.check()?);
+    host_events.clear();
+    host.modify(&source, Event::readable(0))?;
+    host.wait(&mut host_events, Some(Duration::from_millis(50)))?;
+    assert!(host_events.is_empty());
+
+    host.delete(&source)?;
+    Ok(())
+}
@@ -0,0 +1,52 @@
+#![cfg(feature = "metrics")]
+
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use polling::Poller;
+
+#[test]
+fn wait_hook() -> io::Result<()> {
+    let poller = Poller::new()?;
+    let blocked = Arc::new(Mutex::new(Vec::new()));
+    let recorded = blocked.clone();
+    poller.set_wait_hook(move |d| recorded.lock().unwrap().push(d));
+
+    let mut events = Vec::new();
+    poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    poller.wait(&mut events, Some(Duration::from_millis(50)))?;
+    poller.notify()?;
+    poller.wait(&mut events, None)?;
+
+    let blocked = blocked.lock().unwrap();
+    assert_eq!(blocked.len(), 3);
+    assert!(blocked[1] >= Duration::from_millis(50));
+    assert!(blocked[2] < Duration::from_secs(1));
+
+    Ok(())
+}
+
+#[test]
+fn wait_hook_replaces_itself() -> io::Result<()> {
+    let poller = Arc::new(Poller::new()?);
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    // The first wait installs a hook counting the following ones.
+    let weak = Arc::downgrade(&poller);
+    let counter = calls.clone();
+    poller.set_wait_hook(move |_| {
+        let counter = counter.clone();
+        weak.upgrade().unwrap().set_wait_hook(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+    });
+
+    let mut events = Vec::new();
+    poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
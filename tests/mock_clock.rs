@@ -0,0 +1,50 @@
+#![cfg(feature = "test-util")]
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use polling::{Event, MockClock, Poller};
+
+#[test]
+fn firing_order() -> io::Result<()> {
+    let clock = MockClock::new();
+    let poller = Poller::with_mock_clock(clock.clone())?;
+    let mut events = Vec::new();
+
+    let now = clock.now();
+    poller.arm_timer(3, now + Duration::from_secs(30))?;
+    poller.arm_timer(1, now + Duration::from_secs(10))?;
+    poller.arm_timer(2, now + Duration::from_secs(20))?;
+    assert_eq!(poller.time_remaining(), Some(Duration::from_secs(10)));
+
+    poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    assert!(events.is_empty());
+
+    clock.advance(Duration::from_secs(10));
+    assert_eq!(poller.time_remaining(), Some(Duration::from_secs(0)));
+    poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    assert_eq!(events, [Event::readable(1)]);
+
+    clock.advance(Duration::from_secs(20));
+    events.clear();
+    poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    assert_eq!(events, [Event::readable(2), Event::readable(3)]);
+    assert_eq!(poller.time_remaining(), None);
+
+    Ok(())
+}
+
+#[test]
+fn timeout_follows_clock() -> io::Result<()> {
+    let clock = MockClock::new();
+    let poller = Poller::with_mock_clock(clock.clone())?;
+    let mut events = Vec::new();
+
+    // Expired on the mock clock, so the wait doesn't block.
+    poller.arm_timer(1, clock.now())?;
+    let start = Instant::now();
+    poller.wait(&mut events, None)?;
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    Ok(())
+}
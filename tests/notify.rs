@@ -1,9 +1,11 @@
 use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Condvar, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use easy_parallel::Parallel;
-use polling::Poller;
+use polling::{Event, Poller};
 
 #[test]
 fn simple() -> io::Result<()> {
@@ -34,3 +36,126 @@ fn concurrent() -> io::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn condvar() -> io::Result<()> {
+    let poller = Poller::new()?;
+    let ready = Mutex::new(false);
+    let condvar = Condvar::new();
+    let mut events = Vec::new();
+
+    Parallel::new()
+        .add(|| {
+            let mut guard = ready.lock().unwrap();
+            while !*guard {
+                guard = condvar.wait(guard).unwrap();
+            }
+        })
+        .add(|| {
+            thread::sleep(Duration::from_millis(10));
+            *ready.lock().unwrap() = true;
+            poller.notify_on_condvar(&condvar).unwrap();
+        })
+        .finish(|| {
+            while !*ready.lock().unwrap() {
+                poller.wait(&mut events, None).unwrap();
+            }
+        });
+
+    assert!(events.is_empty());
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn waker_handle() -> io::Result<()> {
+    let poller = Poller::new()?;
+    let waker = poller.waker_handle();
+    let mut events = Vec::new();
+
+    for _ in 0..2 {
+        let waker = waker.clone();
+        Parallel::new()
+            .add(move || {
+                thread::sleep(Duration::from_millis(10));
+                waker.wake();
+            })
+            .finish(|| poller.wait(&mut events, None).unwrap());
+    }
+    assert!(events.is_empty());
+
+    // Still usable after the poller is gone.
+    drop(poller);
+    waker.wake();
+
+    Ok(())
+}
+
+#[test]
+fn as_event() -> io::Result<()> {
+    let poller = Poller::builder().notify_as_event(7).build()?;
+    let mut events = Vec::new();
+
+    poller.notify()?;
+    poller.notify()?;
+    poller.wait(&mut events, None)?;
+    assert_eq!(events, [Event::readable(7)]);
+
+    // Internal wakeups aren't reported.
+    events.clear();
+    poller.arm_timer(1, Instant::now() + Duration::from_secs(10))?;
+    poller.wait(&mut events, Some(Duration::from_millis(10)))?;
+    assert!(events.is_empty());
+
+    assert!(Poller::builder()
+        .notify_as_event(std::usize::MAX)
+        .build()
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn as_event_key_reserved() -> io::Result<()> {
+    let poller = Poller::builder().notify_as_event(7).build()?;
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+
+    let err = poller.add(&listener, Event::readable(7)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    let err = poller.arm_timer(7, Instant::now()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    poller.add(&listener, Event::readable(1))?;
+    let err = poller.modify(&listener, Event::readable(7)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    poller.delete(&listener)
+}
+
+#[test]
+fn order() -> io::Result<()> {
+    let poller = Poller::builder().notify_as_event(0).build()?;
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let stream = TcpStream::connect(listener.local_addr()?)?;
+    let mut events = Vec::new();
+
+    let now = Instant::now();
+    poller.arm_timer(3, now)?;
+    poller.arm_timer(2, now - Duration::from_millis(1))?;
+    poller.arm_timer(1, now)?;
+    poller.notify()?;
+    poller.add(&stream, Event::writable(4))?;
+
+    // Wait for the I/O event, so everything is pending at once.
+    thread::sleep(Duration::from_millis(50));
+    poller.wait(&mut events, None)?;
+    assert_eq!(
+        events,
+        [
+            Event::writable(4),
+            Event::readable(0),
+            Event::readable(2),
+            Event::readable(1),
+            Event::readable(3),
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use std::io::{self, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use polling::{Event, Poller};
+
+#[test]
+fn events_on_another_thread() -> io::Result<()> {
+    let (a, mut b) = tcp_pair()?;
+    let poller = Poller::builder().track_registrations().build()?;
+    poller.add(&a, Event::readable(1))?;
+    poller.arm_timer(2, Instant::now())?;
+
+    b.write_all(&[1])?;
+    thread::sleep(Duration::from_millis(10));
+
+    let batch = poller.poll_raw(Some(Duration::from_secs(1)))?;
+    let events = thread::spawn(move || batch.events().collect::<Vec<_>>())
+        .join()
+        .unwrap();
+    assert_eq!(events, [Event::readable(1), Event::readable(2)]);
+
+    // The bookkeeping is updated before the batch is converted.
+    assert_eq!(poller.interest(&a), Some(Event::none(1)));
+
+    Ok(())
+}
+
+#[test]
+fn notification() -> io::Result<()> {
+    let poller = Poller::new()?;
+    poller.notify()?;
+
+    let batch = poller.poll_raw(None)?;
+    assert_eq!(batch.events().count(), 0);
+
+    Ok(())
+}
+
+fn tcp_pair() -> io::Result<(TcpStream, TcpStream)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let a = TcpStream::connect(listener.local_addr()?)?;
+    let (b, _) = listener.accept()?;
+    Ok((a, b))
+}
@@ -72,3 +72,52 @@ fn above_ms() -> io::Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn without_timer() -> io::Result<()> {
+    let poller = Poller::builder().without_timer().build()?;
+    let mut events = Vec::new();
+
+    let dur = Duration::from_micros(100);
+
+    for _ in 0..100 {
+        let now = Instant::now();
+        let n = poller.wait(&mut events, Some(dur))?;
+        let elapsed = now.elapsed();
+
+        assert_eq!(n, 0);
+        assert!(elapsed >= dur);
+    }
+
+    assert!(poller.timer_fallback_reason().is_none());
+    Ok(())
+}
+
+#[test]
+fn software_timer() -> io::Result<()> {
+    let poller = Poller::builder().without_timer().software_timer().build()?;
+    let mut events = Vec::new();
+
+    let dur = Duration::from_micros(100);
+
+    // Without the software timer, the timeout is rounded up to a millisecond, so a single wait
+    // shorter than that shows it works. A loaded machine may overshoot most of them.
+    let mut below_ms = false;
+    for _ in 0..1_000 {
+        let now = Instant::now();
+        poller.wait(&mut events, Some(dur))?;
+        let elapsed = now.elapsed();
+
+        assert!(elapsed >= dur);
+        if elapsed < Duration::from_millis(1) {
+            below_ms = true;
+            break;
+        }
+    }
+    assert!(below_ms);
+
+    // A timeout too large for an instant doesn't arm it.
+    poller.notify()?;
+    poller.wait(&mut events, Some(Duration::from_secs(std::u64::MAX)))?;
+    Ok(())
+}
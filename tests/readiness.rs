@@ -0,0 +1,110 @@
+use std::io::{self, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use polling::{Event, Poller};
+
+#[test]
+fn readable_and_writable() -> io::Result<()> {
+    let (stream, mut peer) = tcp_pair()?;
+    let poller = Poller::new()?;
+    let mut events = Vec::new();
+
+    // Queue inbound data while the send buffer is empty.
+    peer.write_all(&[1])?;
+    poller.add(&stream, Event::readable(1))?;
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events, [Event::readable(1)]);
+
+    events.clear();
+    poller.modify(&stream, Event::all(1))?;
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+
+    if cfg!(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "dragonfly",
+    )) {
+        // kqueue reports readability and writability through separate filters.
+        while events.len() < 2 {
+            poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+        }
+        assert!(events.iter().any(|ev| ev.readable));
+        assert!(events.iter().any(|ev| ev.writable));
+    } else {
+        assert_eq!(events, [Event::all(1)]);
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn receive_low_water_mark() -> io::Result<()> {
+    let (stream, mut peer) = tcp_pair()?;
+    let poller = Poller::new()?;
+    let mut events = Vec::new();
+
+    poller.add_with_lowat(&stream, Event::readable(1), Some(4), None)?;
+
+    // Not readable until the low-water mark is reached.
+    peer.write_all(&[1, 2])?;
+    poller.wait(&mut events, Some(Duration::from_millis(100)))?;
+    assert!(events.is_empty());
+
+    peer.write_all(&[3, 4])?;
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events, [Event::readable(1)]);
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn add_nonblocking() -> io::Result<()> {
+    use std::io::Read;
+
+    let (mut stream, _peer) = tcp_pair()?;
+    let poller = Poller::new()?;
+
+    poller.add_nonblocking(&stream, Event::readable(1))?;
+
+    // Reading with nothing queued no longer blocks.
+    let err = stream.read(&mut [0; 1]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn deferred_accept() -> io::Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    listener.set_nonblocking(true)?;
+    let poller = Poller::new()?;
+    let mut events = Vec::new();
+
+    poller.add_listener(&listener, 1, Some(Duration::from_secs(5)))?;
+
+    // An idle connection is held back.
+    let mut client = TcpStream::connect(listener.local_addr()?)?;
+    poller.wait(&mut events, Some(Duration::from_millis(200)))?;
+    assert!(events.is_empty());
+
+    client.write_all(&[1])?;
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events, [Event::readable(1)]);
+    listener.accept()?;
+
+    Ok(())
+}
+
+fn tcp_pair() -> io::Result<(TcpStream, TcpStream)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let a = TcpStream::connect(listener.local_addr()?)?;
+    let (b, _) = listener.accept()?;
+    Ok((a, b))
+}
@@ -0,0 +1,99 @@
+#![cfg(unix)]
+
+use std::fs::File;
+use std::io;
+use std::os::unix::net::UnixStream;
+
+#[test]
+fn no_error() -> io::Result<()> {
+    let (a, b) = UnixStream::pair()?;
+    assert!(polling::socket_error(&a)?.is_none());
+    assert!(polling::socket_error(&b)?.is_none());
+    Ok(())
+}
+
+#[test]
+fn not_a_socket() -> io::Result<()> {
+    let file = File::open("/dev/null")?;
+    assert!(polling::socket_error(&file).is_err());
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn connection_refused() -> io::Result<()> {
+    use std::net::UdpSocket;
+    use std::time::Duration;
+
+    use polling::{Event, Poller};
+
+    // Find a port nobody listens on.
+    let addr = UdpSocket::bind("127.0.0.1:0")?.local_addr()?;
+
+    let socket = UdpSocket::bind("127.0.0.1:0")?;
+    socket.set_nonblocking(true)?;
+    socket.connect(addr)?;
+
+    let poller = Poller::new()?;
+    poller.add(&socket, Event::readable(1))?;
+
+    // The ICMP port unreachable reply is stored as the socket's pending error.
+    socket.send(&[1])?;
+    let mut events = Vec::new();
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events.len(), 1);
+    assert!(events[0].readable);
+
+    let err = polling::socket_error(&socket)?.unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+
+    // Reading the error clears it.
+    assert!(polling::socket_error(&socket)?.is_none());
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn reset_with_writable_interest() -> io::Result<()> {
+    use std::net::{TcpListener, TcpStream};
+    use std::os::unix::io::AsRawFd;
+    use std::time::Duration;
+
+    use polling::{Event, Poller};
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let socket = TcpStream::connect(listener.local_addr()?)?;
+    let (peer, _) = listener.accept()?;
+
+    let poller = Poller::new()?;
+    poller.add(&socket, Event::writable(1))?;
+
+    // Closing with a zero linger timeout resets the connection.
+    let linger = libc::linger {
+        l_onoff: 1,
+        l_linger: 0,
+    };
+    let res = unsafe {
+        libc::setsockopt(
+            peer.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_LINGER,
+            &linger as *const libc::linger as *const libc::c_void,
+            std::mem::size_of::<libc::linger>() as libc::socklen_t,
+        )
+    };
+    if res == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    drop(peer);
+
+    // The reset wakes up a write-only handler, which finds the error with `socket_error()`.
+    let mut events = Vec::new();
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events.len(), 1);
+    assert!(events[0].writable);
+
+    let err = polling::socket_error(&socket)?.unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+    Ok(())
+}
@@ -30,3 +30,19 @@ fn non_blocking() -> io::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn last_wake_time() -> io::Result<()> {
+    let poller = Poller::new()?;
+    let mut events = Vec::new();
+
+    let start = Instant::now();
+    poller.wait(&mut events, Some(Duration::from_millis(100)))?;
+    let end = Instant::now();
+
+    let wake = poller.last_wake_time().unwrap();
+    assert!(wake >= start + Duration::from_millis(100));
+    assert!(wake <= end);
+
+    Ok(())
+}
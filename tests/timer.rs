@@ -0,0 +1,71 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use polling::{Event, Poller};
+
+#[test]
+fn order() -> io::Result<()> {
+    let poller = Poller::new()?;
+    let mut events = Vec::new();
+
+    let now = Instant::now();
+    poller.arm_timer(2, now + Duration::from_millis(200))?;
+    poller.arm_timer(1, now + Duration::from_millis(100))?;
+
+    while events.len() < 2 {
+        poller.wait(&mut events, None)?;
+    }
+
+    assert_eq!(events, [Event::readable(1), Event::readable(2)]);
+    assert!(now.elapsed() >= Duration::from_millis(200));
+
+    Ok(())
+}
+
+#[test]
+fn shortens_timeout() -> io::Result<()> {
+    let poller = Poller::new()?;
+    let mut events = Vec::new();
+
+    let start = Instant::now();
+    poller.arm_timer(1, start + Duration::from_millis(100))?;
+
+    while events.is_empty() {
+        poller.wait(&mut events, Some(Duration::from_secs(10)))?;
+    }
+    let elapsed = start.elapsed();
+
+    assert_eq!(events, [Event::readable(1)]);
+    assert!(elapsed >= Duration::from_millis(100));
+    assert!(elapsed < Duration::from_secs(10));
+
+    Ok(())
+}
+
+#[test]
+fn until_key() -> io::Result<()> {
+    let poller = Poller::new()?;
+    let mut events = Vec::new();
+
+    let now = Instant::now();
+    poller.arm_timer(1, now + Duration::from_millis(50))?;
+    poller.arm_timer(7, now + Duration::from_millis(100))?;
+    poller.arm_timer(2, now + Duration::from_secs(10))?;
+
+    // Skip the notification caused by arming the timers.
+    poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+
+    let n = poller.wait_until_key(&mut events, 7, Some(Duration::from_secs(5)))?;
+    assert_eq!(n, 2);
+    assert_eq!(events, [Event::readable(1), Event::readable(7)]);
+
+    // A timeout too large for an instant waits without a deadline.
+    events.clear();
+    poller.arm_timer(8, Instant::now() + Duration::from_millis(10))?;
+    poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    let huge = Some(Duration::from_secs(std::u64::MAX));
+    poller.wait_until_key(&mut events, 8, huge)?;
+    assert_eq!(events, [Event::readable(8)]);
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+#![cfg(unix)]
+
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::time::Duration;
+
+use polling::{Event, Poller};
+
+#[test]
+fn readable_on_datagram() -> io::Result<()> {
+    let (sender, receiver) = UnixDatagram::pair()?;
+    receiver.set_nonblocking(true)?;
+
+    let poller = Poller::new()?;
+    poller.add(&receiver, Event::readable(1))?;
+
+    let mut events = Vec::new();
+    poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    assert!(events.is_empty());
+
+    sender.send(&[1, 2, 3])?;
+    sender.send(&[4, 5])?;
+
+    events.clear();
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events, [Event::readable(1)]);
+
+    // Exactly one datagram is received at a time.
+    let mut buf = [0; 16];
+    assert_eq!(receiver.recv(&mut buf)?, 3);
+    assert_eq!(&buf[..3], &[1, 2, 3]);
+
+    // The second datagram is still queued.
+    poller.modify(&receiver, Event::readable(1))?;
+    events.clear();
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events, [Event::readable(1)]);
+    assert_eq!(receiver.recv(&mut buf)?, 2);
+    assert_eq!(&buf[..2], &[4, 5]);
+
+    // Nothing is left.
+    poller.modify(&receiver, Event::readable(1))?;
+    events.clear();
+    poller.wait(&mut events, Some(Duration::from_secs(0)))?;
+    assert!(events.is_empty());
+    assert_eq!(
+        receiver.recv(&mut buf).unwrap_err().kind(),
+        io::ErrorKind::WouldBlock
+    );
+
+    Ok(())
+}
+
+#[test]
+fn writable_on_datagram() -> io::Result<()> {
+    let (sender, _receiver) = UnixDatagram::pair()?;
+    sender.set_nonblocking(true)?;
+
+    let poller = Poller::new()?;
+    poller.add(&sender, Event::writable(1))?;
+
+    let mut events = Vec::new();
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events, [Event::writable(1)]);
+
+    Ok(())
+}
+
+#[test]
+fn bytes_ready_on_datagram() -> io::Result<()> {
+    let (sender, receiver) = UnixDatagram::pair()?;
+    receiver.set_nonblocking(true)?;
+    assert_eq!(polling::bytes_ready(&receiver)?, 0);
+
+    let poller = Poller::new()?;
+    poller.add(&receiver, Event::readable(1))?;
+    sender.send(&[1, 2, 3])?;
+
+    let mut events = Vec::new();
+    poller.wait(&mut events, Some(Duration::from_secs(1)))?;
+    assert_eq!(events, [Event::readable(1)]);
+    assert!(polling::bytes_ready(&receiver)? >= 3);
+
+    Ok(())
+}